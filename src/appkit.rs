@@ -0,0 +1,307 @@
+use std::ffi::c_void;
+use std::os::raw::c_long;
+
+use objc::runtime::{Object, NO};
+use objc_foundation::{object_struct, NSArray, NSData, NSString};
+use objc_foundation::{INSArray, INSData, INSObject, INSString};
+use objc_id::{Id, ShareId};
+
+use crate::{ClipboardBackend, ClipboardError, Content, Type};
+
+#[allow(non_upper_case_globals)]
+const NSUTF8StringEncoding: u8 = 4;
+type NSPasteboardType = *mut NSString;
+
+object_struct!(NSPasteboard);
+object_struct!(NSPasteboardItem);
+
+#[allow(improper_ctypes)]
+#[link(name = "AppKit", kind = "framework")]
+extern "C" {
+    static NSPasteboardTypeTIFF: NSPasteboardType;
+    static NSPasteboardTypePNG: NSPasteboardType;
+    static NSPasteboardTypePDF: NSPasteboardType;
+    static NSPasteboardTypeHTML: NSPasteboardType;
+    static NSPasteboardTypeRTF: NSPasteboardType;
+    static NSPasteboardTypeTabularText: NSPasteboardType;
+    static NSPasteboardTypeString: NSPasteboardType;
+    static NSPasteboardTypeFileURL: NSPasteboardType;
+}
+
+impl From<NSPasteboardType> for Type {
+    fn from(ty: NSPasteboardType) -> Self {
+        unsafe {
+            if msg_send![ty, isEqualToString: NSPasteboardTypeTIFF] {
+                Self::TIFF
+            } else if msg_send![ty, isEqualToString: NSPasteboardTypePNG] {
+                Self::PNG
+            } else if msg_send![ty, isEqualToString: NSPasteboardTypePDF] {
+                Self::PDF
+            } else if msg_send![ty, isEqualToString: NSPasteboardTypeHTML] {
+                Self::HTML
+            } else if msg_send![ty, isEqualToString: NSPasteboardTypeRTF] {
+                Self::RTF
+            } else if msg_send![ty, isEqualToString: NSPasteboardTypeTabularText] {
+                Self::TabularText
+            } else if msg_send![ty, isEqualToString: NSPasteboardTypeString] {
+                Self::String
+            } else if msg_send![ty, isEqualToString: NSPasteboardTypeFileURL] {
+                Self::FileUrl
+            } else {
+                Self::Custom((*ty).as_str().to_string())
+            }
+        }
+    }
+}
+
+impl From<&Type> for Id<NSString> {
+    fn from(ty: &Type) -> Self {
+        unsafe {
+            let ty = match ty {
+                Type::FileUrl => NSPasteboardTypeFileURL,
+                Type::HTML => NSPasteboardTypeHTML,
+                Type::PDF => NSPasteboardTypePDF,
+                Type::PNG => NSPasteboardTypePNG,
+                Type::RTF => NSPasteboardTypeRTF,
+                Type::String => NSPasteboardTypeString,
+                Type::TIFF => NSPasteboardTypeTIFF,
+                Type::TabularText => NSPasteboardTypeTabularText,
+                Type::Custom(uti) => return NSString::from_str(uti),
+            };
+            Id::from_ptr(ty)
+        }
+    }
+}
+
+/// `[NSPasteboard generalPasteboard]`, the system-wide clipboard.
+#[derive(Debug, Clone)]
+pub struct GeneralPasteBoard {
+    board: ShareId<NSPasteboard>,
+}
+
+// SAFETY: `generalPasteboard` is a process-wide singleton that NSPasteboard
+// documents as safe to use from any thread, and `ShareId` only retains and
+// releases it, which is atomic. Callers off the main thread wrap each call
+// in `autoreleasepool` so the objects it autoreleases don't pile up.
+unsafe impl Send for GeneralPasteBoard {}
+
+impl GeneralPasteBoard {
+    pub fn new() -> Result<Self, ClipboardError> {
+        unsafe {
+            let cls = class!(NSPasteboard);
+            let board: *mut NSPasteboard = msg_send![cls, generalPasteboard];
+            if board.is_null() {
+                return Err("Can't get generalPasteboard".into());
+            }
+            let board = Id::from_ptr(board);
+            Ok(Self { board })
+        }
+    }
+}
+
+impl ClipboardBackend for GeneralPasteBoard {
+    fn change_count(&self) -> i64 {
+        unsafe {
+            let change_count: c_long = msg_send![self.board, changeCount];
+            change_count as i64
+        }
+    }
+
+    fn types(&self) -> Result<Vec<Type>, ClipboardError> {
+        unsafe {
+            let types: Id<NSArray<NSPasteboardType>> = Id::from_ptr(msg_send![self.board, types]);
+            let types = (0u64..msg_send![types, count])
+                .map(|idx| {
+                    let ty: NSPasteboardType = msg_send![types, objectAtIndex: idx];
+                    Type::from(ty)
+                })
+                .collect();
+            Ok(types)
+        }
+    }
+
+    fn get_contents(&self, ty: Type) -> Result<Content, ClipboardError> {
+        unsafe {
+            let pasteboard_type = Id::<NSString>::from(&ty);
+            let content = if ty.is_text() {
+                let string: *mut NSString = msg_send![self.board, stringForType: &*pasteboard_type];
+                if string.is_null() {
+                    return Err(ClipboardError::TypeUnavailable(ty));
+                }
+                let string: Id<NSString> = Id::from_ptr(string);
+                Content::String(string.as_str().to_string().into_boxed_str())
+            } else {
+                let data: *mut NSData = msg_send![self.board, dataForType: &*pasteboard_type];
+                if data.is_null() {
+                    return Err(ClipboardError::TypeUnavailable(ty));
+                }
+                let data: Id<NSData> = Id::from_ptr(data);
+                Content::Data(data.bytes().to_vec().into_boxed_slice())
+            };
+            Ok(content)
+        }
+    }
+
+    fn write_all(&self, items: Vec<(Type, Content)>) -> Result<(), ClipboardError> {
+        // Attributed text is published in the form its type calls for.
+        let items: Vec<_> = items
+            .into_iter()
+            .map(|(ty, content)| match content {
+                Content::Attributed(text) => {
+                    let string = text.render(&ty);
+                    (ty, Content::String(string.into()))
+                }
+                content => (ty, content),
+            })
+            .collect();
+        unsafe {
+            // Build every object up front so a failed allocation leaves the
+            // current clipboard untouched.
+            let representations = items
+                .iter()
+                .map(|(_, content)| Representation::new(content))
+                .collect::<Result<Vec<_>, _>>()?;
+            let _: c_long = msg_send![self.board, clearContents];
+            for ((ty, _), representation) in items.iter().zip(representations) {
+                let pasteboard_type = Id::<NSString>::from(ty);
+                let ok: bool = match representation {
+                    Representation::Data(data) => {
+                        msg_send![self.board, setData: &*data forType: &*pasteboard_type]
+                    }
+                    Representation::String(string) => {
+                        msg_send![self.board, setString: &*string forType: &*pasteboard_type]
+                    }
+                };
+                if !ok {
+                    return Err(ClipboardError::WriteRejected(ty.clone()));
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn item_contents(&self, ty: Type) -> Result<Vec<Content>, ClipboardError> {
+        unsafe {
+            let pasteboard_type = Id::<NSString>::from(&ty);
+            let items: *mut NSArray<NSPasteboardItem> = msg_send![self.board, pasteboardItems];
+            if items.is_null() {
+                return Err(ClipboardError::TypeUnavailable(ty));
+            }
+            let items: Id<NSArray<NSPasteboardItem>> = Id::from_ptr(items);
+            let mut contents = Vec::new();
+            for item in items.to_vec() {
+                if ty.is_text() {
+                    let string: *mut NSString = msg_send![item, stringForType: &*pasteboard_type];
+                    if !string.is_null() {
+                        contents.push(Content::String((*string).as_str().into()));
+                    }
+                } else {
+                    let data: *mut NSData = msg_send![item, dataForType: &*pasteboard_type];
+                    if !data.is_null() {
+                        contents.push(Content::Data((*data).bytes().into()));
+                    }
+                }
+            }
+            if contents.is_empty() {
+                return Err(ClipboardError::TypeUnavailable(ty));
+            }
+            Ok(contents)
+        }
+    }
+
+    fn write_items(&self, items: Vec<Vec<(Type, Content)>>) -> Result<(), ClipboardError> {
+        unsafe {
+            // Pasteboard items may outlive this call, so unlike `write_all`
+            // they get their own copies of the bytes.
+            let mut objects = Vec::with_capacity(items.len());
+            for item in &items {
+                let object = NSPasteboardItem::new();
+                for (ty, content) in item {
+                    let pasteboard_type = Id::<NSString>::from(ty);
+                    let ok: bool = match content {
+                        Content::Data(data) => {
+                            let data = NSData::with_bytes(data);
+                            msg_send![object, setData: &*data forType: &*pasteboard_type]
+                        }
+                        Content::String(string) => {
+                            let string = NSString::from_str(string);
+                            msg_send![object, setString: &*string forType: &*pasteboard_type]
+                        }
+                        Content::Attributed(text) => {
+                            let string = NSString::from_str(&text.render(ty));
+                            msg_send![object, setString: &*string forType: &*pasteboard_type]
+                        }
+                    };
+                    if !ok {
+                        return Err(ClipboardError::WriteRejected(ty.clone()));
+                    }
+                }
+                objects.push(object);
+            }
+            let objects = NSArray::from_vec(objects);
+            let _: c_long = msg_send![self.board, clearContents];
+            let ok: bool = msg_send![self.board, writeObjects: &*objects];
+            match items.first().and_then(|item| item.first()) {
+                Some((ty, _)) if !ok => Err(ClipboardError::WriteRejected(ty.clone())),
+                _ => Ok(()),
+            }
+        }
+    }
+}
+
+enum Representation {
+    Data(Id<NSData>),
+    String(Id<NSString>),
+}
+
+impl Representation {
+    /// Wrap `content` without copying; the caller must keep it alive until
+    /// the pasteboard has taken the object.
+    unsafe fn new(content: &Content) -> Result<Self, ClipboardError> {
+        match content {
+            Content::Data(data) => {
+                let nsdata_cls = class!(NSData);
+                let data: *mut NSData = msg_send![nsdata_cls, dataWithBytesNoCopy: (data.as_ptr() as *const c_void)
+                                                                           length: data.len()
+                                                                     freeWhenDone: NO];
+                if data.is_null() {
+                    return Err(ClipboardError::AllocationFailed("NSData"));
+                }
+                Ok(Self::Data(Id::from_ptr(data)))
+            }
+            Content::String(string) => {
+                let nsstring_cls = class!(NSString);
+                let nsstring_instance: *mut Object = msg_send![nsstring_cls, alloc];
+                let string: *mut NSString = msg_send![nsstring_instance, initWithBytesNoCopy: (string.as_ptr() as *const c_void)
+                                                                                      length: string.len()
+                                                                                    encoding: NSUTF8StringEncoding
+                                                                                freeWhenDone: NO];
+                if string.is_null() {
+                    return Err(ClipboardError::AllocationFailed("NSString"));
+                }
+                Ok(Self::String(Id::from_retained_ptr(string)))
+            }
+            Content::Attributed(text) => Ok(Self::String(NSString::from_str(text.text()))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn static_link() {
+        unsafe {
+            let mut static_data = vec![
+                NSPasteboardTypeTIFF, NSPasteboardTypePNG, NSPasteboardTypePDF,
+                NSPasteboardTypeHTML, NSPasteboardTypeRTF, NSPasteboardTypeTabularText,
+                NSPasteboardTypeString, NSPasteboardTypeFileURL
+            ];
+            static_data.drain(..).for_each(|instance| {
+                let str_ptr: *const i8 = msg_send![instance, UTF8String];
+                assert!(!str_ptr.is_null());
+            })
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use crate::{ClipboardError, Content, Type};
+
+/// Run `f` inside an autorelease pool, so the AppKit objects a backend call
+/// autoreleases are freed when it returns. Threads we spawn have no pool of
+/// their own and would otherwise leak them.
+#[cfg(target_os = "macos")]
+pub(crate) fn autoreleasepool<T>(f: impl FnOnce() -> T) -> T {
+    objc::rc::autoreleasepool(f)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub(crate) fn autoreleasepool<T>(f: impl FnOnce() -> T) -> T {
+    f()
+}
+
+/// The operations `PasteBoard` needs from a pasteboard server.
+///
+/// `GeneralPasteBoard` talks to AppKit on macOS, `MemoryPasteBoard` keeps
+/// everything in process so the crate can be exercised anywhere.
+pub trait ClipboardBackend {
+    /// Counter bumped every time the pasteboard ownership changes.
+    fn change_count(&self) -> i64;
+
+    /// Types currently on the pasteboard, in the order they were written.
+    fn types(&self) -> Result<Vec<Type>, ClipboardError>;
+
+    fn get_contents(&self, ty: Type) -> Result<Content, ClipboardError>;
+
+    /// Clear the pasteboard and publish `content` as `ty`.
+    fn write_contents(&self, content: Content, ty: Type) -> Result<(), ClipboardError> {
+        self.write_all(vec![(ty, content)])
+    }
+
+    /// Clear the pasteboard once and publish every representation in
+    /// `items`, so readers see them together under a single change count.
+    fn write_all(&self, items: Vec<(Type, Content)>) -> Result<(), ClipboardError>;
+
+    /// The `ty` representation of every pasteboard item that has one, such
+    /// as each of several copied files. The default reads only the first.
+    fn item_contents(&self, ty: Type) -> Result<Vec<Content>, ClipboardError> {
+        self.get_contents(ty).map(|content| vec![content])
+    }
+
+    /// Clear the pasteboard once and publish each entry of `items` as a
+    /// separate pasteboard item. The default supports a single item.
+    fn write_items(&self, mut items: Vec<Vec<(Type, Content)>>) -> Result<(), ClipboardError> {
+        match items.len() {
+            0 | 1 => self.write_all(items.pop().unwrap_or_default()),
+            _ => Err("Backend can't publish several pasteboard items".into()),
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use crate::convert::{
+    html_to_rtf, html_to_text, rtf_to_html, rtf_to_text, tables_from_html, Table,
+};
+use crate::{ClipboardBackend, ClipboardError, Content, PasteBoard, Type};
+
+impl Type {
+    /// Formatted text first, falling back to plain text.
+    pub const RICH_TEXT: &'static [Type] = &[Type::RTF, Type::HTML, Type::String];
+    /// Bitmaps first, then PDF.
+    pub const IMAGE: &'static [Type] = &[Type::PNG, Type::TIFF, Type::PDF];
+    pub const PLAIN_TEXT: &'static [Type] = &[Type::String];
+
+    /// Types `get_best` can convert into this one, in the order they are
+    /// tried.
+    fn sources(&self) -> &'static [Type] {
+        match self {
+            Type::String => &[Type::HTML, Type::RTF, Type::TabularText],
+            Type::HTML => &[Type::RTF, Type::TabularText],
+            Type::RTF => &[Type::HTML],
+            Type::TabularText => &[Type::HTML],
+            #[cfg(feature = "image")]
+            Type::PNG => &[Type::TIFF],
+            #[cfg(feature = "image")]
+            Type::TIFF => &[Type::PNG],
+            _ => &[],
+        }
+    }
+}
+
+/// `content` of type `from` converted to `to`, or `None` when it holds
+/// nothing convertible.
+fn convert(from: &Type, content: &Content, to: &Type) -> Option<Content> {
+    #[cfg(feature = "image")]
+    if let (Type::PNG | Type::TIFF, Content::Data(data)) = (from, content) {
+        let image = match from {
+            Type::PNG => crate::Image::from_png(data),
+            _ => crate::Image::from_tiff(data),
+        };
+        let encoded = match to {
+            Type::PNG => image.ok()?.to_png(),
+            _ => image.ok()?.to_tiff(),
+        };
+        return encoded.ok().map(|data| Content::Data(data.into()));
+    }
+    let text = content.as_str()?;
+    let converted = match (from, to) {
+        (Type::HTML, Type::String) => html_to_text(text),
+        (Type::RTF, Type::String) => rtf_to_text(text),
+        (Type::TabularText, Type::String) => text.to_string(),
+        (Type::RTF, Type::HTML) => rtf_to_html(text),
+        (Type::TabularText, Type::HTML) => Table::from_tsv(text).to_html(),
+        (Type::HTML, Type::RTF) => html_to_rtf(text),
+        (Type::HTML, Type::TabularText) => tables_from_html(text).first()?.to_tsv(),
+        _ => return None,
+    };
+    Some(Content::String(converted.into()))
+}
+
+impl<B: ClipboardBackend> PasteBoard<B> {
+    /// The first of `preferred` on the clipboard, as its type and content.
+    ///
+    /// A type that isn't there is converted from one that is where the
+    /// crate knows how: plain text from HTML, RTF or tabular text, HTML and
+    /// RTF from each other, HTML from and tabular text to the first HTML
+    /// table, and with the `image` feature PNG and TIFF from each other.
+    /// Conversion is only a fallback: any of `preferred` that is there as is
+    /// wins over converting into an earlier one.
+    ///
+    /// ```
+    /// use rich_clipboard_macos::{Content, MemoryPasteBoard, PasteBoard, Type};
+    ///
+    /// let board = PasteBoard::with_backend(MemoryPasteBoard::new());
+    /// board.write_contents(Content::String("<b>Hi</b>".into()), Type::HTML).unwrap();
+    /// let (ty, text) = board.get_best(Type::PLAIN_TEXT).unwrap().unwrap();
+    /// assert_eq!((ty, text.as_str()), (Type::String, Some("Hi")));
+    /// ```
+    pub fn get_best(&self, preferred: &[Type]) -> Result<Option<(Type, Content)>, ClipboardError> {
+        let available = self.board.types()?;
+        let preferred: Vec<Type> = preferred.iter().map(|ty| ty.clone().canonical()).collect();
+        for ty in preferred.iter().filter(|ty| available.contains(ty)) {
+            if let Some(content) = self.get_available(ty)? {
+                return Ok(Some((ty.clone(), content)));
+            }
+        }
+        for ty in &preferred {
+            for source in ty
+                .sources()
+                .iter()
+                .filter(|source| available.contains(source))
+            {
+                let Some(content) = self.get_available(source)? else {
+                    continue;
+                };
+                if let Some(converted) = convert(source, &content, ty) {
+                    return Ok(Some((ty.clone(), converted)));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Read `ty`, treating it as absent if it vanished since `types`.
+    fn get_available(&self, ty: &Type) -> Result<Option<Content>, ClipboardError> {
+        match self.board.get_contents(ty.clone()) {
+            Ok(content) => Ok(Some(content)),
+            Err(ClipboardError::TypeUnavailable(_)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryPasteBoard;
+
+    #[test]
+    fn preference_order() {
+        let board = PasteBoard::with_backend(MemoryPasteBoard::new());
+        assert_eq!(board.get_best(Type::RICH_TEXT).unwrap(), None);
+
+        board
+            .writer()
+            .add(Type::HTML, Content::String("<b>Hi</b>".into()))
+            .add(Type::String, Content::String("Hi".into()))
+            .add(
+                Type::Custom("com.example.note".into()),
+                Content::Data([1].into()),
+            )
+            .commit()
+            .unwrap();
+        let best = |preferred: &[Type]| board.get_best(preferred).unwrap().unwrap();
+        assert_eq!(best(&[Type::HTML, Type::String]).0, Type::HTML);
+        assert_eq!(best(&[Type::String, Type::HTML]).0, Type::String);
+        assert_eq!(
+            best(&[Type::Custom("com.example.note".into())]),
+            (
+                Type::Custom("com.example.note".into()),
+                Content::Data([1].into())
+            )
+        );
+        assert_eq!(
+            best(&[Type::Custom("public.utf8-plain-text".into())]).0,
+            Type::String
+        );
+        assert_eq!(board.get_best(Type::IMAGE).unwrap(), None);
+        assert_eq!(board.get_best(&[]).unwrap(), None);
+
+        // The HTML that is there beats RTF converted from it.
+        assert_eq!(
+            best(Type::RICH_TEXT),
+            (Type::HTML, Content::String("<b>Hi</b>".into()))
+        );
+        let (ty, rtf) = best(&[Type::RTF]);
+        assert_eq!(ty, Type::RTF);
+        assert!(rtf.as_str().unwrap().contains(r"{\b Hi}"));
+    }
+
+    #[test]
+    fn conversions() {
+        let board = PasteBoard::with_backend(MemoryPasteBoard::new());
+        let best = |preferred: &[Type]| board.get_best(preferred).unwrap();
+        let text = |ty: Type, content: &str| Some((ty, Content::String(content.into())));
+
+        board
+            .write_contents(Content::String(r"{\rtf1 a\par b}".into()), Type::RTF)
+            .unwrap();
+        assert_eq!(best(Type::PLAIN_TEXT), text(Type::String, "a\nb"));
+        assert_eq!(
+            best(&[Type::HTML]),
+            text(
+                Type::HTML,
+                "<meta charset=\"utf-8\"><div>a</div><div>b</div>"
+            )
+        );
+        assert_eq!(best(&[Type::TabularText]), None);
+
+        board
+            .write_contents(
+                Content::String(
+                    "<table><tr><th>a</th><th>b</th></tr><tr><td>1</td><td>2</td></tr></table>"
+                        .into(),
+                ),
+                Type::HTML,
+            )
+            .unwrap();
+        assert_eq!(
+            best(&[Type::TabularText]),
+            text(Type::TabularText, "a\tb\n1\t2\n")
+        );
+
+        board
+            .write_contents(Content::String("a\tb\n1\t2".into()), Type::TabularText)
+            .unwrap();
+        assert_eq!(best(Type::PLAIN_TEXT), text(Type::String, "a\tb\n1\t2"));
+        let (_, html) = best(&[Type::HTML]).unwrap();
+        assert!(html.as_str().unwrap().contains("<td>2</td>"));
+
+        board
+            .write_contents(Content::Data([0x89, b'P'].into()), Type::PNG)
+            .unwrap();
+        assert_eq!(best(&[Type::String]), None);
+        assert_eq!(best(Type::IMAGE).unwrap().0, Type::PNG);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn images() {
+        let board = PasteBoard::with_backend(MemoryPasteBoard::new());
+        let image = crate::Image::new(1, 1, vec![1, 2, 3, 4]).unwrap();
+        board
+            .write_contents(Content::Data(image.to_tiff().unwrap().into()), Type::TIFF)
+            .unwrap();
+        let (ty, png) = board.get_best(&[Type::PNG]).unwrap().unwrap();
+        assert_eq!(ty, Type::PNG);
+        assert_eq!(crate::Image::from_png(png.as_bytes()).unwrap(), image);
+        // A TIFF-only clipboard isn't re-encoded when TIFF is acceptable.
+        assert_eq!(board.get_best(Type::IMAGE).unwrap().unwrap().0, Type::TIFF);
+
+        board
+            .write_contents(Content::Data([0x89, b'P'].into()), Type::PNG)
+            .unwrap();
+        assert_eq!(board.get_best(&[Type::TIFF]).unwrap(), None);
+    }
+}
@@ -0,0 +1,355 @@
+//! `pbcopy`/`pbpaste` with type selection.
+//!
+//! ```text
+//! rclip copy [--type TYPE[=FILE]]...   write stdin or FILEs to the clipboard
+//! rclip paste [--type TYPE]            write one representation to stdout
+//! rclip types                          list the types on the clipboard
+//! ```
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+use rich_clipboard_macos::{sniff, ClipboardBackend, ClipboardError, Content, PasteBoard, Type};
+
+const USAGE: &str = "\
+usage: rclip copy [--type TYPE[=FILE]]...
+       rclip paste [--type TYPE]
+       rclip types
+
+TYPE is one of tiff, png, pdf, html, rtf, tabular, string, file-url or a
+UTI such as com.microsoft.Word.Doc. `copy` reads stdin for a TYPE without
+FILE and detects the type of stdin when no --type is given; several --type
+options publish several representations at once.
+
+exit status: 0 success, 1 clipboard failure, 2 usage or I/O error,
+3 type not on the clipboard, 4 type not detected or content doesn't fit it,
+5 clipboard rejected the write";
+
+#[derive(Debug)]
+enum Failure {
+    Usage(String),
+    Io(io::Error),
+    Clipboard(ClipboardError),
+    Undetected,
+    NotText(Type),
+}
+
+impl Failure {
+    fn exit_code(&self) -> u8 {
+        match self {
+            Self::Usage(_) | Self::Io(_) => 2,
+            Self::Clipboard(ClipboardError::NoNewContent)
+            | Self::Clipboard(ClipboardError::TypeUnavailable(_)) => 3,
+            Self::Clipboard(ClipboardError::UnsupportedType(_))
+            | Self::Clipboard(ClipboardError::ContentMismatch(_))
+            | Self::Undetected
+            | Self::NotText(_) => 4,
+            Self::Clipboard(ClipboardError::WriteRejected(_)) => 5,
+            Self::Clipboard(_) => 1,
+        }
+    }
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Usage(msg) => write!(f, "{}\n\n{}", msg, USAGE),
+            Self::Io(err) => write!(f, "{}", err),
+            Self::Clipboard(err) => write!(f, "{}", err),
+            Self::Undetected => write!(f, "can't detect the type of the input, pass --type"),
+            Self::NotText(ty) => write!(f, "{} content must be UTF-8 text", type_name(ty)),
+        }
+    }
+}
+
+impl From<io::Error> for Failure {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<ClipboardError> for Failure {
+    fn from(err: ClipboardError) -> Self {
+        Self::Clipboard(err)
+    }
+}
+
+fn parse_type(name: &str) -> Result<Type, Failure> {
+    let ty = match name.to_ascii_lowercase().as_str() {
+        "tiff" => Type::TIFF,
+        "png" => Type::PNG,
+        "pdf" => Type::PDF,
+        "html" => Type::HTML,
+        "rtf" => Type::RTF,
+        "tabular" | "tsv" => Type::TabularText,
+        "string" | "text" => Type::String,
+        "file-url" => Type::FileUrl,
+        _ if name.contains('.') => Type::from_uti(name),
+        _ => return Err(Failure::Usage(format!("unknown type `{}`", name))),
+    };
+    Ok(ty)
+}
+
+fn type_name(ty: &Type) -> &str {
+    match ty {
+        Type::TIFF => "tiff",
+        Type::PNG => "png",
+        Type::PDF => "pdf",
+        Type::HTML => "html",
+        Type::RTF => "rtf",
+        Type::TabularText => "tabular",
+        Type::String => "string",
+        Type::FileUrl => "file-url",
+        Type::Custom(uti) => uti,
+    }
+}
+
+/// Best guess at the type of piped-in bytes, falling back to plain text.
+fn detect(bytes: &[u8]) -> Option<Type> {
+    sniff(bytes).or_else(|| std::str::from_utf8(bytes).is_ok().then_some(Type::String))
+}
+
+/// Text types go through `setString:`, so they have to be valid UTF-8.
+fn content(ty: &Type, bytes: Vec<u8>) -> Result<Content, Failure> {
+    if !ty.is_text() {
+        return Ok(Content::Data(bytes.into_boxed_slice()));
+    }
+    String::from_utf8(bytes)
+        .map(|string| Content::String(string.into_boxed_str()))
+        .map_err(|_| Failure::NotText(ty.clone()))
+}
+
+/// Split `--type VALUE`, `--type=VALUE` and `-t VALUE` into their values.
+fn type_options(
+    command: &str,
+    mut args: impl Iterator<Item = String>,
+) -> Result<Vec<String>, Failure> {
+    let mut values = Vec::new();
+    while let Some(arg) = args.next() {
+        if let Some(value) = arg.strip_prefix("--type=") {
+            values.push(value.to_string());
+        } else if arg == "--type" || arg == "-t" {
+            match args.next() {
+                Some(value) => values.push(value),
+                None => return Err(Failure::Usage(format!("{} needs a value", arg))),
+            }
+        } else {
+            return Err(Failure::Usage(format!(
+                "unexpected argument `{}` to {}",
+                arg, command
+            )));
+        }
+    }
+    Ok(values)
+}
+
+fn copy<B: ClipboardBackend>(
+    board: &PasteBoard<B>,
+    specs: Vec<String>,
+    stdin: &mut dyn Read,
+) -> Result<(), Failure> {
+    let mut read_stdin = false;
+    let mut stdin_bytes = || -> Result<Vec<u8>, Failure> {
+        if read_stdin {
+            return Err(Failure::Usage("only one --type can read stdin".into()));
+        }
+        read_stdin = true;
+        let mut bytes = Vec::new();
+        stdin.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    };
+
+    let mut writer = board.writer();
+    if specs.is_empty() {
+        let bytes = stdin_bytes()?;
+        let ty = detect(&bytes).ok_or(Failure::Undetected)?;
+        writer = writer.add(ty.clone(), content(&ty, bytes)?);
+    }
+    for spec in specs {
+        let (ty, bytes) = match spec.split_once('=') {
+            Some((ty, path)) => (parse_type(ty)?, fs::read(path)?),
+            None => (parse_type(&spec)?, stdin_bytes()?),
+        };
+        writer = writer.add(ty.clone(), content(&ty, bytes)?);
+    }
+    writer.commit()?;
+    Ok(())
+}
+
+fn paste<B: ClipboardBackend>(
+    board: &PasteBoard<B>,
+    specs: Vec<String>,
+    stdout: &mut dyn Write,
+) -> Result<(), Failure> {
+    let ty = match specs.as_slice() {
+        [] => Type::String,
+        [ty] => parse_type(ty)?,
+        _ => return Err(Failure::Usage("paste takes a single --type".into())),
+    };
+    match board.get_contents(ty, false)? {
+        Content::Data(data) => stdout.write_all(&data)?,
+        Content::String(string) => stdout.write_all(string.as_bytes())?,
+        Content::Attributed(text) => stdout.write_all(text.text().as_bytes())?,
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+fn types<B: ClipboardBackend>(
+    board: &PasteBoard<B>,
+    stdout: &mut dyn Write,
+) -> Result<(), Failure> {
+    for ty in board.types()? {
+        writeln!(stdout, "{}", type_name(&ty))?;
+    }
+    Ok(())
+}
+
+fn run<B: ClipboardBackend>(
+    board: &PasteBoard<B>,
+    mut args: impl Iterator<Item = String>,
+    stdin: &mut dyn Read,
+    stdout: &mut dyn Write,
+) -> Result<(), Failure> {
+    let command = args.next().unwrap_or_default();
+    match command.as_str() {
+        "copy" => copy(board, type_options("copy", args)?, stdin),
+        "paste" => paste(board, type_options("paste", args)?, stdout),
+        "types" => match args.next() {
+            None => types(board, stdout),
+            Some(arg) => Err(Failure::Usage(format!(
+                "unexpected argument `{}` to types",
+                arg
+            ))),
+        },
+        "help" | "-h" | "--help" => {
+            writeln!(stdout, "{}", USAGE)?;
+            Ok(())
+        }
+        "" => Err(Failure::Usage("missing command".into())),
+        command => Err(Failure::Usage(format!("unknown command `{}`", command))),
+    }
+}
+
+/// The system clipboard, which only macOS has.
+#[cfg(target_os = "macos")]
+fn open() -> Result<PasteBoard, ClipboardError> {
+    PasteBoard::new()
+}
+
+#[cfg(not(target_os = "macos"))]
+fn open() -> Result<PasteBoard<rich_clipboard_macos::MemoryPasteBoard>, ClipboardError> {
+    Err("there is no system clipboard to use off macOS".into())
+}
+
+fn main() -> ExitCode {
+    let result = open().map_err(Failure::from).and_then(|board| {
+        run(
+            &board,
+            std::env::args().skip(1),
+            &mut io::stdin().lock(),
+            &mut io::stdout().lock(),
+        )
+    });
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(failure) => {
+            eprintln!("rclip: {}", failure);
+            ExitCode::from(failure.exit_code())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rich_clipboard_macos::MemoryPasteBoard;
+
+    fn rclip(
+        board: &PasteBoard<MemoryPasteBoard>,
+        args: &str,
+        stdin: &[u8],
+    ) -> Result<Vec<u8>, u8> {
+        let mut stdout = Vec::new();
+        let args = args.split_whitespace().map(String::from);
+        match run(board, args, &mut &*stdin, &mut stdout) {
+            Ok(()) => Ok(stdout),
+            Err(failure) => Err(failure.exit_code()),
+        }
+    }
+
+    fn board() -> PasteBoard<MemoryPasteBoard> {
+        PasteBoard::with_backend(MemoryPasteBoard::new())
+    }
+
+    #[test]
+    fn copy_paste() {
+        let board = board();
+        rclip(&board, "copy --type html", b"<b>hi</b>").unwrap();
+        assert_eq!(rclip(&board, "types", b"").unwrap(), b"html\n");
+        assert_eq!(rclip(&board, "paste -t html", b"").unwrap(), b"<b>hi</b>");
+        assert_eq!(rclip(&board, "paste", b""), Err(3));
+
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        rclip(&board, "copy --type=png", png).unwrap();
+        assert_eq!(rclip(&board, "paste --type png", b"").unwrap(), png);
+    }
+
+    #[test]
+    fn detection() {
+        let board = board();
+        for (input, ty) in [
+            (&b"\x89PNG\r\n\x1a\nrest"[..], "png\n"),
+            (b"II*\0rest", "tiff\n"),
+            (b"%PDF-1.7", "pdf\n"),
+            (b"{\\rtf1\\ansi hi}", "rtf\n"),
+            (b"  <!DOCTYPE html><p>hi", "html\n"),
+            (b"MM\0*\0\0\0\x08", "tiff\n"),
+            (b"GIF89a\x01\0", "com.compuserve.gif\n"),
+            (b"<b>bold</b>", "html\n"),
+            (b"<p>files start with %PDF-1.7</p>", "html\n"),
+            (b"plain text", "string\n"),
+        ] {
+            rclip(&board, "copy", input).unwrap();
+            assert_eq!(rclip(&board, "types", b"").unwrap(), ty.as_bytes());
+            assert_eq!(rclip(&board, "paste --type", b""), Err(2));
+        }
+        assert_eq!(rclip(&board, "copy", b"\xff\xfe\0"), Err(4));
+        assert_eq!(rclip(&board, "copy --type html", b"\xff"), Err(4));
+    }
+
+    #[test]
+    fn multiple_representations() {
+        let dir = std::env::temp_dir().join(format!("rclip-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let html = dir.join("page.html");
+        fs::write(&html, "<i>hi</i>").unwrap();
+
+        let board = board();
+        let args = format!(
+            "copy --type html={} --type string -t org.chromium.source-url={}",
+            html.display(),
+            html.display()
+        );
+        rclip(&board, &args, b"hi").unwrap();
+        assert_eq!(board.backend().change_count(), 1);
+        assert_eq!(
+            rclip(&board, "types", b"").unwrap(),
+            b"html\nstring\norg.chromium.source-url\n"
+        );
+        assert_eq!(rclip(&board, "paste", b"").unwrap(), b"hi");
+        assert_eq!(rclip(&board, "copy -t string -t html", b"hi"), Err(2));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn usage() {
+        let board = board();
+        assert_eq!(rclip(&board, "", b""), Err(2));
+        assert_eq!(rclip(&board, "frobnicate", b""), Err(2));
+        assert_eq!(rclip(&board, "copy --type bogus", b""), Err(2));
+        assert_eq!(rclip(&board, "types extra", b""), Err(2));
+        assert!(rclip(&board, "--help", b"").is_ok());
+    }
+}
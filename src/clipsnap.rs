@@ -0,0 +1,369 @@
+//! The `.clipsnap` container for persisting a `ClipboardSnapshot`.
+//!
+//! All integers are little endian.
+//!
+//! ```text
+//! header
+//!   magic          8 bytes  "CLIPSNAP"
+//!   version        u16      format version of the writer
+//!   min_version    u16      oldest reader version able to read the file
+//!   header_len     u16      total header length, magic included
+//!   reserved       u16      zero
+//!   count          u32      number of representations
+//!   ...            header_len - 20 bytes added by later versions
+//!
+//! representation, repeated `count` times
+//!   body_len       u32      length of the body below
+//!   body
+//!     tag          u8       type, see `tag`
+//!     kind         u8       0 = Content::Data, 1 = Content::String
+//!     uti_len      u16      length of the UTI, non-zero only for custom types
+//!     uti          uti_len bytes of UTF-8
+//!     payload_len  u64
+//!     payload      payload_len bytes
+//!     ...          body_len - known fields, added by later versions
+//!   crc32          u32      CRC-32 (IEEE) of the body
+//! ```
+//!
+//! Readers accept any file whose `min_version` is not newer than their own
+//! `VERSION`. They skip header and body bytes they don't know about and drop
+//! representations with a tag they don't recognise, so later versions can
+//! extend the format without breaking older readers.
+
+use std::io::{self, Read, Write};
+
+use crate::{ClipboardSnapshot, Content, Type};
+
+pub const MAGIC: [u8; 8] = *b"CLIPSNAP";
+/// Format version written by this crate.
+pub const VERSION: u16 = 1;
+/// File extension for snapshot files.
+pub const EXTENSION: &str = "clipsnap";
+
+const HEADER_LEN: u16 = 20;
+const BODY_FIXED_LEN: usize = 12;
+const KIND_DATA: u8 = 0;
+const KIND_STRING: u8 = 1;
+
+fn tag(ty: &Type) -> u8 {
+    match ty {
+        Type::TIFF => 1,
+        Type::PNG => 2,
+        Type::PDF => 3,
+        Type::HTML => 4,
+        Type::RTF => 5,
+        Type::TabularText => 6,
+        Type::String => 7,
+        Type::FileUrl => 8,
+        Type::Custom(_) => 0xff,
+    }
+}
+
+fn from_tag(tag: u8, uti: String) -> Option<Type> {
+    let ty = match tag {
+        1 => Type::TIFF,
+        2 => Type::PNG,
+        3 => Type::PDF,
+        4 => Type::HTML,
+        5 => Type::RTF,
+        6 => Type::TabularText,
+        7 => Type::String,
+        8 => Type::FileUrl,
+        0xff => Type::Custom(uti),
+        _ => return None,
+    };
+    Some(ty)
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Write `snapshot` in the current format version.
+pub fn write<W: Write>(mut writer: W, snapshot: &ClipboardSnapshot) -> io::Result<()> {
+    let count =
+        u32::try_from(snapshot.items().len()).map_err(|_| invalid("too many representations"))?;
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&HEADER_LEN.to_le_bytes())?;
+    writer.write_all(&0u16.to_le_bytes())?;
+    writer.write_all(&count.to_le_bytes())?;
+
+    for (ty, content) in snapshot.items() {
+        let uti = match ty {
+            Type::Custom(uti) => uti.as_bytes(),
+            _ => &[],
+        };
+        let uti_len = u16::try_from(uti.len()).map_err(|_| invalid("UTI is too long"))?;
+        let rendered;
+        let (kind, payload) = match content {
+            Content::Data(data) => (KIND_DATA, &**data),
+            Content::String(string) => (KIND_STRING, string.as_bytes()),
+            Content::Attributed(text) => {
+                rendered = text.render(ty);
+                (KIND_STRING, rendered.as_bytes())
+            }
+        };
+
+        let mut body = Vec::with_capacity(BODY_FIXED_LEN + uti.len() + payload.len());
+        body.push(tag(ty));
+        body.push(kind);
+        body.extend_from_slice(&uti_len.to_le_bytes());
+        body.extend_from_slice(uti);
+        body.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        body.extend_from_slice(payload);
+        let body_len =
+            u32::try_from(body.len()).map_err(|_| invalid("representation is too large"))?;
+
+        writer.write_all(&body_len.to_le_bytes())?;
+        writer.write_all(&body)?;
+        writer.write_all(&crc32(&body).to_le_bytes())?;
+    }
+    Ok(())
+}
+
+pub fn to_vec(snapshot: &ClipboardSnapshot) -> Vec<u8> {
+    let mut buf = Vec::new();
+    write(&mut buf, snapshot).expect("writing to a Vec can't fail");
+    buf
+}
+
+/// Read a snapshot written by this or any compatible later version.
+pub fn read<R: Read>(mut reader: R) -> io::Result<ClipboardSnapshot> {
+    let mut header = [0u8; HEADER_LEN as usize];
+    reader.read_exact(&mut header)?;
+    if header[..8] != MAGIC {
+        return Err(invalid("not a clipsnap file"));
+    }
+    let min_version = u16::from_le_bytes([header[10], header[11]]);
+    if min_version > VERSION {
+        return Err(invalid(format!(
+            "clipsnap version {} is not supported",
+            min_version
+        )));
+    }
+    let header_len = u16::from_le_bytes([header[12], header[13]]);
+    if header_len < HEADER_LEN {
+        return Err(invalid("truncated clipsnap header"));
+    }
+    let count = u32::from_le_bytes([header[16], header[17], header[18], header[19]]);
+    skip(&mut reader, u64::from(header_len - HEADER_LEN))?;
+
+    let mut items = Vec::new();
+    for _ in 0..count {
+        let mut len = [0u8; 4];
+        reader.read_exact(&mut len)?;
+        let body_len = u32::from_le_bytes(len) as usize;
+        let body = read_exact_vec(&mut reader, body_len)?;
+        let mut crc = [0u8; 4];
+        reader.read_exact(&mut crc)?;
+        if crc32(&body) != u32::from_le_bytes(crc) {
+            return Err(invalid("checksum mismatch"));
+        }
+        if let Some(item) = parse_body(&body)? {
+            items.push(item);
+        }
+    }
+    Ok(ClipboardSnapshot::new(items))
+}
+
+pub fn from_slice(bytes: &[u8]) -> io::Result<ClipboardSnapshot> {
+    read(bytes)
+}
+
+fn parse_body(body: &[u8]) -> io::Result<Option<(Type, Content)>> {
+    let truncated = || invalid("truncated representation");
+    if body.len() < BODY_FIXED_LEN {
+        return Err(truncated());
+    }
+    let (tag, kind) = (body[0], body[1]);
+    let uti_len = u16::from_le_bytes([body[2], body[3]]) as usize;
+    let rest = &body[4..];
+    let (uti, rest) = (rest.get(..uti_len).ok_or_else(truncated)?, &rest[uti_len..]);
+    let payload_len = rest.get(..8).ok_or_else(truncated)?;
+    let payload_len = u64::from_le_bytes(payload_len.try_into().unwrap());
+    let payload = usize::try_from(payload_len)
+        .ok()
+        .and_then(|len| rest[8..].get(..len))
+        .ok_or_else(truncated)?;
+
+    let uti = String::from_utf8(uti.to_vec()).map_err(|_| invalid("UTI is not UTF-8"))?;
+    let ty = match from_tag(tag, uti) {
+        Some(ty) => ty,
+        None => return Ok(None),
+    };
+    let content = match kind {
+        KIND_DATA => Content::Data(payload.into()),
+        KIND_STRING => {
+            let string = std::str::from_utf8(payload).map_err(|_| invalid("text is not UTF-8"))?;
+            Content::String(string.into())
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some((ty, content)))
+}
+
+fn read_exact_vec<R: Read>(reader: &mut R, len: usize) -> io::Result<Vec<u8>> {
+    // Don't trust `len` for the allocation: a corrupt file shouldn't make us
+    // reserve gigabytes up front.
+    let mut buf = Vec::new();
+    reader.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(buf)
+}
+
+fn skip<R: Read>(reader: &mut R, len: u64) -> io::Result<()> {
+    let skipped = io::copy(&mut reader.take(len), &mut io::sink())?;
+    if skipped != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, &byte| {
+        CRC32_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> ClipboardSnapshot {
+        ClipboardSnapshot::new(vec![
+            (Type::HTML, Content::String("<b>a</b>".into())),
+            (Type::String, Content::String("a".into())),
+            (Type::PNG, Content::Data(Box::new([0x89, b'P', b'N', b'G']))),
+            (
+                Type::Custom("org.chromium.source-url".into()),
+                Content::Data(Box::new(*b"https://example.com")),
+            ),
+        ])
+    }
+
+    #[test]
+    fn crc() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn round_trip() {
+        let snapshot = sample();
+        let bytes = to_vec(&snapshot);
+        assert_eq!(&bytes[..8], b"CLIPSNAP");
+        assert_eq!(from_slice(&bytes).unwrap(), snapshot);
+        assert_eq!(
+            from_slice(&to_vec(&ClipboardSnapshot::default())).unwrap(),
+            ClipboardSnapshot::default()
+        );
+    }
+
+    #[test]
+    fn corruption() {
+        let bytes = to_vec(&sample());
+        let mut flipped = bytes.clone();
+        let last = flipped.len() - 6;
+        flipped[last] ^= 1;
+        assert_eq!(
+            from_slice(&flipped).unwrap_err().to_string(),
+            "checksum mismatch"
+        );
+        assert!(from_slice(&bytes[..bytes.len() - 1]).is_err());
+        assert!(from_slice(b"NOTASNAP").is_err());
+        for len in 0..bytes.len() {
+            assert!(from_slice(&bytes[..len]).is_err());
+        }
+    }
+
+    /// Hand-build a file as a hypothetical version 2 writer would, with a
+    /// longer header, extra body fields and a type tag v1 doesn't know.
+    fn future_file(min_version: u16) -> Vec<u8> {
+        let mut file = Vec::new();
+        file.extend_from_slice(&MAGIC);
+        file.extend_from_slice(&2u16.to_le_bytes());
+        file.extend_from_slice(&min_version.to_le_bytes());
+        file.extend_from_slice(&24u16.to_le_bytes());
+        file.extend_from_slice(&0u16.to_le_bytes());
+        file.extend_from_slice(&2u32.to_le_bytes());
+        file.extend_from_slice(&[0xaa; 4]);
+
+        for (tag, payload) in [(7u8, &b"hello"[..]), (0x42, &b"???"[..])] {
+            let mut body = vec![tag, KIND_STRING, 0, 0];
+            body.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+            body.extend_from_slice(payload);
+            body.extend_from_slice(b"future fields");
+            file.extend_from_slice(&(body.len() as u32).to_le_bytes());
+            file.extend_from_slice(&body);
+            file.extend_from_slice(&crc32(&body).to_le_bytes());
+        }
+        file
+    }
+
+    #[test]
+    fn forward_compatible() {
+        let snapshot = from_slice(&future_file(1)).unwrap();
+        assert_eq!(
+            snapshot,
+            ClipboardSnapshot::new(vec![(Type::String, Content::String("hello".into()))])
+        );
+    }
+
+    #[test]
+    fn incompatible_version() {
+        let err = from_slice(&future_file(2)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "clipsnap version 2 is not supported");
+    }
+
+    /// Bytes of a version 1 file; must stay readable by every later version.
+    #[test]
+    fn version_1_fixture() {
+        #[rustfmt::skip]
+        let file = [
+            b'C', b'L', b'I', b'P', b'S', b'N', b'A', b'P',
+            1, 0, 1, 0, 20, 0, 0, 0,
+            1, 0, 0, 0,
+            13, 0, 0, 0,
+            7, 1, 0, 0,
+            1, 0, 0, 0, 0, 0, 0, 0,
+            b'a',
+            0x38, 0x49, 0x6c, 0xc4,
+        ];
+        assert_eq!(
+            from_slice(&file).unwrap(),
+            ClipboardSnapshot::new(vec![(Type::String, Content::String("a".into()))])
+        );
+        assert_eq!(
+            to_vec(&ClipboardSnapshot::new(vec![(
+                Type::String,
+                Content::String("a".into())
+            )])),
+            file
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use std::ops::Range;
+
+use crate::Type;
+
+/// An sRGB colour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Alignment {
+    #[default]
+    Left,
+    Center,
+    Right,
+    Justified,
+}
+
+/// Character formatting shared by a run of text. `None` means the reader's
+/// default.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RunStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+    /// Font family name.
+    pub font: Option<String>,
+    /// Font size in half points, the unit of RTF's `\fs`.
+    pub size: Option<u32>,
+    pub color: Option<Color>,
+    pub background: Option<Color>,
+    /// Target URL when the run is a hyperlink.
+    pub link: Option<String>,
+    /// Alignment of the paragraph the run belongs to. A paragraph takes the
+    /// alignment of its first run.
+    pub alignment: Alignment,
+}
+
+/// Text split into runs of uniform formatting, the model RTF and HTML are
+/// converted through. Paragraphs are separated by `\n`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "Runs", into = "Runs")
+)]
+pub struct AttributedText {
+    text: String,
+    /// Each run's end offset in `text` and its style. Runs are never empty
+    /// and neighbours never share a style.
+    runs: Vec<(usize, RunStyle)>,
+}
+
+impl AttributedText {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_html(html: &str) -> Self {
+        super::text::render_html(html)
+    }
+
+    pub fn from_rtf(rtf: &str) -> Self {
+        super::rtf::read(rtf)
+    }
+
+    pub fn to_html(&self) -> String {
+        super::html::write(self)
+    }
+
+    pub fn to_rtf(&self) -> String {
+        super::rtf::write(self)
+    }
+
+    /// The representation to publish as `ty`: HTML, RTF or else plain text.
+    pub(crate) fn render(&self, ty: &Type) -> String {
+        match ty {
+            Type::HTML => self.to_html(),
+            Type::RTF => self.to_rtf(),
+            _ => self.text.clone(),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn len(&self) -> usize {
+        self.text.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// The runs of text and their styles, in order.
+    pub fn runs(&self) -> impl Iterator<Item = (&str, &RunStyle)> {
+        self.ranges()
+            .map(|(range, style)| (&self.text[range], style))
+    }
+
+    /// The byte range of each run in `text()` and its style, in order.
+    pub fn ranges(&self) -> impl Iterator<Item = (Range<usize>, &RunStyle)> {
+        let mut start = 0;
+        self.runs.iter().map(move |(end, style)| {
+            let range = start..*end;
+            start = *end;
+            (range, style)
+        })
+    }
+
+    /// Append `text` in `style`.
+    pub fn push_str(&mut self, text: &str, style: &RunStyle) {
+        if text.is_empty() {
+            return;
+        }
+        self.text.push_str(text);
+        match self.runs.last_mut() {
+            Some((end, last)) if last == style => *end = self.text.len(),
+            _ => self.runs.push((self.text.len(), style.clone())),
+        }
+    }
+
+    /// Append whitespace in the style of the text before it.
+    pub(crate) fn extend(&mut self, text: &str) {
+        match self.runs.pop() {
+            Some((_, style)) => {
+                self.text.push_str(text);
+                self.runs.push((self.text.len(), style));
+            }
+            None => self.push_str(text, &RunStyle::default()),
+        }
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<char> {
+        let ch = self.text.pop()?;
+        self.fix_runs();
+        Some(ch)
+    }
+
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.text.truncate(len);
+        self.fix_runs();
+    }
+
+    fn fix_runs(&mut self) {
+        let len = self.text.len();
+        while self.runs.len() > 1 && self.runs[self.runs.len() - 2].0 >= len {
+            self.runs.pop();
+        }
+        match self.runs.last_mut() {
+            Some((end, _)) if len > 0 => *end = len,
+            _ => self.runs.clear(),
+        }
+    }
+}
+
+impl From<&str> for AttributedText {
+    fn from(text: &str) -> Self {
+        let mut attributed = Self::new();
+        attributed.push_str(text, &RunStyle::default());
+        attributed
+    }
+}
+
+impl From<String> for AttributedText {
+    fn from(text: String) -> Self {
+        Self::from(text.as_str())
+    }
+}
+
+/// Serialized form of `AttributedText`, which can't hold invalid offsets.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Runs(Vec<(String, RunStyle)>);
+
+#[cfg(feature = "serde")]
+impl From<Runs> for AttributedText {
+    fn from(runs: Runs) -> Self {
+        let mut text = Self::new();
+        for (run, style) in &runs.0 {
+            text.push_str(run, style);
+        }
+        text
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<AttributedText> for Runs {
+    fn from(text: AttributedText) -> Self {
+        Self(
+            text.runs()
+                .map(|(run, style)| (run.to_string(), style.clone()))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs() {
+        let bold = RunStyle {
+            bold: true,
+            ..RunStyle::default()
+        };
+        let mut text = AttributedText::from("plain ");
+        text.push_str("bold", &bold);
+        text.push_str("er", &bold);
+        text.push_str("", &RunStyle::default());
+        text.push_str(" tail", &RunStyle::default());
+        assert_eq!(text.text(), "plain bolder tail");
+        assert_eq!(
+            text.runs().collect::<Vec<_>>(),
+            vec![
+                ("plain ", &RunStyle::default()),
+                ("bolder", &bold),
+                (" tail", &RunStyle::default()),
+            ]
+        );
+
+        text.truncate(10);
+        assert_eq!(text.pop(), Some('d'));
+        assert_eq!(
+            text.runs().collect::<Vec<_>>(),
+            vec![("plain ", &RunStyle::default()), ("bol", &bold)]
+        );
+        text.truncate(0);
+        assert_eq!(text, AttributedText::new());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let mut text = AttributedText::from("a");
+        text.push_str(
+            "b",
+            &RunStyle {
+                link: Some("https://example.com".into()),
+                alignment: Alignment::Center,
+                ..RunStyle::default()
+            },
+        );
+        let json = serde_json::to_string(&text).unwrap();
+        assert_eq!(serde_json::from_str::<AttributedText>(&json).unwrap(), text);
+    }
+}
@@ -0,0 +1,162 @@
+//! Just enough CSS to read and write inline `style` attributes.
+
+use super::Color;
+
+/// The `property: value` pairs of an inline style, properties lowercased.
+pub(crate) fn declarations(style: &str) -> impl Iterator<Item = (String, &str)> {
+    style.split(';').filter_map(|decl| {
+        let (property, value) = decl.split_once(':')?;
+        let value = value.trim().trim_end_matches("!important").trim();
+        Some((property.trim().to_ascii_lowercase(), value))
+    })
+}
+
+const NAMED_COLORS: &[(&str, Color)] = &[
+    ("black", Color::new(0, 0, 0)),
+    ("white", Color::new(255, 255, 255)),
+    ("red", Color::new(255, 0, 0)),
+    ("green", Color::new(0, 128, 0)),
+    ("blue", Color::new(0, 0, 255)),
+    ("yellow", Color::new(255, 255, 0)),
+    ("orange", Color::new(255, 165, 0)),
+    ("purple", Color::new(128, 0, 128)),
+    ("gray", Color::new(128, 128, 128)),
+    ("grey", Color::new(128, 128, 128)),
+    ("silver", Color::new(192, 192, 192)),
+    ("maroon", Color::new(128, 0, 0)),
+    ("navy", Color::new(0, 0, 128)),
+    ("teal", Color::new(0, 128, 128)),
+    ("olive", Color::new(128, 128, 0)),
+    ("lime", Color::new(0, 255, 0)),
+    ("aqua", Color::new(0, 255, 255)),
+    ("cyan", Color::new(0, 255, 255)),
+    ("fuchsia", Color::new(255, 0, 255)),
+    ("magenta", Color::new(255, 0, 255)),
+];
+
+/// Parse `#rgb`, `#rrggbb`, `rgb()`/`rgba()` or a basic colour name.
+pub(crate) fn parse_color(value: &str) -> Option<Color> {
+    let value = value.trim().to_ascii_lowercase();
+    if let Some(hex) = value.strip_prefix('#') {
+        let digit = |idx: usize| u8::from_str_radix(hex.get(idx..idx + 1)?, 16).ok();
+        let pair = |idx: usize| u8::from_str_radix(hex.get(idx..idx + 2)?, 16).ok();
+        return match hex.len() {
+            3 => Some(Color::new(digit(0)? * 17, digit(1)? * 17, digit(2)? * 17)),
+            6 => Some(Color::new(pair(0)?, pair(2)?, pair(4)?)),
+            _ => None,
+        };
+    }
+    if let Some(args) = value
+        .strip_prefix("rgba(")
+        .or_else(|| value.strip_prefix("rgb("))
+    {
+        let mut channels = args
+            .trim_end_matches(')')
+            .split([',', ' ', '/'])
+            .filter(|c| !c.is_empty());
+        let mut channel = || -> Option<u8> {
+            let channel = channels.next()?;
+            let value = match channel.strip_suffix('%') {
+                Some(percent) => percent.parse::<f32>().ok()? * 2.55,
+                None => channel.parse::<f32>().ok()?,
+            };
+            Some(value.round().clamp(0.0, 255.0) as u8)
+        };
+        return Some(Color::new(channel()?, channel()?, channel()?));
+    }
+    NAMED_COLORS
+        .iter()
+        .find(|(name, _)| *name == value)
+        .map(|(_, color)| *color)
+}
+
+pub(crate) fn format_color(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+/// Parse a `font-size` into half points. Relative sizes are taken against
+/// 12pt.
+pub(crate) fn parse_font_size(value: &str) -> Option<u32> {
+    let value = value.trim().to_ascii_lowercase();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f32 = number.parse().ok()?;
+    let points = match unit.trim() {
+        "pt" => number,
+        "px" | "" => number * 0.75,
+        "em" | "rem" => number * 12.0,
+        "%" => number * 0.12,
+        _ => return None,
+    };
+    Some((points * 2.0).round().max(1.0) as u32)
+}
+
+pub(crate) fn format_font_size(half_points: u32) -> String {
+    match half_points % 2 {
+        0 => format!("{}pt", half_points / 2),
+        _ => format!("{}.5pt", half_points / 2),
+    }
+}
+
+/// The first concrete family of a `font-family` list. Generic families other
+/// than `monospace` name no font.
+pub(crate) fn parse_font_family(value: &str) -> Option<String> {
+    for family in value.split(',') {
+        let family = family.trim().trim_matches(['"', '\'']).trim();
+        match family.to_ascii_lowercase().as_str() {
+            "" | "serif" | "sans-serif" | "cursive" | "fantasy" | "system-ui" | "inherit"
+            | "initial" => continue,
+            "monospace" => return Some(MONOSPACE.to_string()),
+            generic if generic.starts_with('-') => continue,
+            _ => return Some(family.to_string()),
+        }
+    }
+    None
+}
+
+/// Font used for `<code>`, `<pre>` and generic monospace text.
+pub(crate) const MONOSPACE: &str = "Courier";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse_color("#F00"), Some(Color::new(255, 0, 0)));
+        assert_eq!(parse_color(" #1a2B3c "), Some(Color::new(26, 43, 60)));
+        assert_eq!(parse_color("rgb(1, 2, 3)"), Some(Color::new(1, 2, 3)));
+        assert_eq!(
+            parse_color("rgba(100%,0%,0%,0.5)"),
+            Some(Color::new(255, 0, 0))
+        );
+        assert_eq!(parse_color("Navy"), Some(Color::new(0, 0, 128)));
+        assert_eq!(parse_color("#12"), None);
+        assert_eq!(parse_color("rgb(1)"), None);
+        assert_eq!(format_color(Color::new(26, 43, 60)), "#1a2b3c");
+    }
+
+    #[test]
+    fn fonts() {
+        assert_eq!(parse_font_size("12pt"), Some(24));
+        assert_eq!(parse_font_size("14px"), Some(21));
+        assert_eq!(parse_font_size("1.5em"), Some(36));
+        assert_eq!(parse_font_size("large"), None);
+        assert_eq!(format_font_size(21), "10.5pt");
+        assert_eq!(
+            parse_font_family("-apple-system, \"Helvetica Neue\", sans-serif"),
+            Some("Helvetica Neue".into())
+        );
+        assert_eq!(parse_font_family("monospace"), Some("Courier".into()));
+        assert_eq!(parse_font_family("serif"), None);
+        assert_eq!(
+            declarations("COLOR: red; font-weight:bold !important;;x").collect::<Vec<_>>(),
+            vec![
+                ("color".to_string(), "red"),
+                ("font-weight".to_string(), "bold")
+            ]
+        );
+    }
+}
@@ -0,0 +1,597 @@
+//! A forgiving HTML tokenizer, good enough for the fragments apps put on the
+//! pasteboard. It doesn't build a tree; converters track nesting themselves.
+
+use super::css;
+use super::{Alignment, AttributedText, RunStyle};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Token {
+    Start {
+        name: String,
+        attrs: Vec<(String, String)>,
+        self_closing: bool,
+    },
+    End {
+        name: String,
+    },
+    /// Text with entities already decoded.
+    Text(String),
+}
+
+impl Token {
+    pub(crate) fn attr(&self, key: &str) -> Option<&str> {
+        match self {
+            Token::Start { attrs, .. } => attrs
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value.as_str()),
+            _ => None,
+        }
+    }
+}
+
+/// Elements whose content is never markup.
+const RAW_TEXT: &[&str] = &["script", "style", "textarea", "title", "xmp"];
+
+/// Elements that never have content or an end tag.
+pub(crate) const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+pub(crate) fn tokenize(html: &str) -> Vec<Token> {
+    let bytes = html.as_bytes();
+    let mut tokens = Vec::new();
+    let mut text_start = 0;
+    let mut pos = 0;
+    // A failed search for `-->` would fail again from every later `<!--`.
+    let mut comments_close = true;
+
+    while pos < bytes.len() {
+        if bytes[pos] != b'<' {
+            pos += 1;
+            continue;
+        }
+        let rest = &bytes[pos + 1..];
+        let end = if rest.starts_with(b"!--") {
+            comments_close
+                .then(|| find(bytes, pos + 4, b"-->"))
+                .flatten()
+                .map(|end| (end + 3, None))
+        } else if rest.starts_with(b"!") || rest.starts_with(b"?") {
+            // Without a `>` further on nothing after this is markup either.
+            match find(bytes, pos, b">") {
+                Some(end) => Some((end + 1, None)),
+                None => break,
+            }
+        } else if rest.first().is_some_and(u8::is_ascii_alphabetic)
+            || (rest.starts_with(b"/") && rest.get(1).is_some_and(u8::is_ascii_alphabetic))
+        {
+            // A tag that runs to the end of the input leaves the rest as
+            // text, rather than rescanning it from every later `<`.
+            match parse_tag(html, pos) {
+                Some((end, token)) => Some((end, Some(token))),
+                None => break,
+            }
+        } else {
+            None
+        };
+        let (end, token) = match end {
+            Some(end) => end,
+            None => {
+                comments_close &= !rest.starts_with(b"!--");
+                pos += 1;
+                continue;
+            }
+        };
+
+        push_text(&mut tokens, &html[text_start..pos]);
+        pos = end;
+        text_start = end;
+        if let Some(token) = token {
+            let raw = match &token {
+                Token::Start {
+                    name, self_closing, ..
+                } if !self_closing && RAW_TEXT.contains(&name.as_str()) => Some(name.clone()),
+                _ => None,
+            };
+            tokens.push(token);
+            if let Some(name) = raw {
+                let close = find_close_tag(bytes, pos, &name).unwrap_or(bytes.len());
+                let text = &html[pos..close];
+                if name == "textarea" || name == "title" {
+                    push_text(&mut tokens, text);
+                } else if !text.is_empty() {
+                    tokens.push(Token::Text(text.to_string()));
+                }
+                pos = close;
+                text_start = close;
+            }
+        }
+    }
+    push_text(&mut tokens, &html[text_start..]);
+    tokens
+}
+
+fn push_text(tokens: &mut Vec<Token>, text: &str) {
+    if !text.is_empty() {
+        tokens.push(Token::Text(decode_entities(text)));
+    }
+}
+
+fn find(bytes: &[u8], from: usize, needle: &[u8]) -> Option<usize> {
+    bytes
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|idx| idx + from)
+}
+
+fn find_close_tag(bytes: &[u8], from: usize, name: &str) -> Option<usize> {
+    let mut pos = from;
+    while let Some(idx) = find(bytes, pos, b"</") {
+        let candidate = &bytes[idx + 2..];
+        if candidate.len() >= name.len()
+            && candidate[..name.len()].eq_ignore_ascii_case(name.as_bytes())
+            && candidate
+                .get(name.len())
+                .is_none_or(|b| b.is_ascii_whitespace() || *b == b'>' || *b == b'/')
+        {
+            return Some(idx);
+        }
+        pos = idx + 2;
+    }
+    None
+}
+
+/// Parse the tag starting at `start`, returning the offset after its `>`.
+fn parse_tag(html: &str, start: usize) -> Option<(usize, Token)> {
+    let bytes = html.as_bytes();
+    let mut pos = start + 1;
+    let closing = bytes[pos] == b'/';
+    if closing {
+        pos += 1;
+    }
+    let name_start = pos;
+    while pos < bytes.len() && !is_tag_delimiter(bytes[pos]) {
+        pos += 1;
+    }
+    let name = html[name_start..pos].to_ascii_lowercase();
+
+    let mut attrs = Vec::new();
+    let mut self_closing = false;
+    loop {
+        while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        match bytes.get(pos)? {
+            b'>' => {
+                pos += 1;
+                break;
+            }
+            b'/' => {
+                pos += 1;
+                if bytes.get(pos) == Some(&b'>') {
+                    self_closing = true;
+                }
+                continue;
+            }
+            _ => {}
+        }
+        let key_start = pos;
+        while pos < bytes.len() && !is_tag_delimiter(bytes[pos]) && bytes[pos] != b'=' {
+            pos += 1;
+        }
+        let key = html[key_start..pos].to_ascii_lowercase();
+        while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        let mut value = String::new();
+        if bytes.get(pos) == Some(&b'=') {
+            pos += 1;
+            while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            match bytes.get(pos)? {
+                quote @ (b'"' | b'\'') => {
+                    let end = bytes[pos + 1..].iter().position(|b| b == quote)? + pos + 1;
+                    value = decode_entities(&html[pos + 1..end]);
+                    pos = end + 1;
+                }
+                _ => {
+                    let value_start = pos;
+                    while pos < bytes.len()
+                        && !bytes[pos].is_ascii_whitespace()
+                        && bytes[pos] != b'>'
+                    {
+                        pos += 1;
+                    }
+                    value = decode_entities(&html[value_start..pos]);
+                }
+            }
+        }
+        if !key.is_empty() && !closing {
+            attrs.push((key, value));
+        }
+    }
+
+    let token = if closing {
+        Token::End { name }
+    } else {
+        let self_closing = self_closing || VOID.contains(&name.as_str());
+        Token::Start {
+            name,
+            attrs,
+            self_closing,
+        }
+    };
+    Some((pos, token))
+}
+
+fn is_tag_delimiter(byte: u8) -> bool {
+    byte.is_ascii_whitespace() || byte == b'>' || byte == b'/'
+}
+
+const ENTITIES: &[(&str, &str)] = &[
+    ("amp", "&"),
+    ("lt", "<"),
+    ("gt", ">"),
+    ("quot", "\""),
+    ("apos", "'"),
+    ("nbsp", "\u{a0}"),
+    ("ensp", "\u{2002}"),
+    ("emsp", "\u{2003}"),
+    ("thinsp", "\u{2009}"),
+    ("zwnj", "\u{200c}"),
+    ("zwj", "\u{200d}"),
+    ("shy", "\u{ad}"),
+    ("copy", "©"),
+    ("reg", "®"),
+    ("trade", "™"),
+    ("hellip", "…"),
+    ("mdash", "—"),
+    ("ndash", "–"),
+    ("lsquo", "‘"),
+    ("rsquo", "’"),
+    ("sbquo", "‚"),
+    ("ldquo", "“"),
+    ("rdquo", "”"),
+    ("bdquo", "„"),
+    ("laquo", "«"),
+    ("raquo", "»"),
+    ("bull", "•"),
+    ("middot", "·"),
+    ("deg", "°"),
+    ("plusmn", "±"),
+    ("times", "×"),
+    ("divide", "÷"),
+    ("sect", "§"),
+    ("para", "¶"),
+    ("cent", "¢"),
+    ("pound", "£"),
+    ("euro", "€"),
+    ("yen", "¥"),
+    ("larr", "←"),
+    ("rarr", "→"),
+    ("uarr", "↑"),
+    ("darr", "↓"),
+    ("frac12", "½"),
+    ("frac14", "¼"),
+    ("frac34", "¾"),
+];
+
+pub(crate) fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(idx) = rest.find('&') {
+        out.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+        match decode_entity(rest) {
+            Some((decoded, len)) => {
+                out.push_str(&decoded);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Decode the entity at the start of `text`, returning it and its length.
+fn decode_entity(text: &str) -> Option<(String, usize)> {
+    let body = &text[1..];
+    if let Some(num) = body.strip_prefix('#') {
+        let (digits, radix, skip) = match num.strip_prefix(['x', 'X']) {
+            Some(hex) => (hex, 16, 3),
+            None => (num, 10, 2),
+        };
+        let len = digits
+            .bytes()
+            .take_while(|b| (*b as char).is_digit(radix))
+            .count();
+        if len == 0 {
+            return None;
+        }
+        let code = u32::from_str_radix(&digits[..len], radix).ok();
+        let ch = code
+            .and_then(char::from_u32)
+            .filter(|ch| *ch != '\0')
+            .unwrap_or('\u{fffd}');
+        let semicolon = usize::from(digits[len..].starts_with(';'));
+        return Some((ch.to_string(), skip + len + semicolon));
+    }
+    let len = body.bytes().take_while(u8::is_ascii_alphanumeric).count();
+    if !body[len..].starts_with(';') {
+        return None;
+    }
+    let name = &body[..len];
+    ENTITIES
+        .iter()
+        .find(|(entity, _)| *entity == name)
+        .map(|(_, decoded)| (decoded.to_string(), len + 2))
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+/// Whether `url` is safe to link to, in either direction: script and
+/// `data:` URLs are not. Browsers ignore whitespace and control characters
+/// in the scheme, so this does too.
+pub(crate) fn is_safe_url(url: &str) -> bool {
+    let scheme: String = url
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_ascii_control())
+        .take_while(|c| *c != ':')
+        .collect::<String>()
+        .to_ascii_lowercase();
+    let has_scheme = url.contains(':') && !scheme.contains(['/', '?', '#']);
+    !has_scheme || !matches!(scheme.as_str(), "javascript" | "vbscript" | "data")
+}
+
+/// Render attributed text as an HTML fragment, one `<div>` per paragraph.
+pub(crate) fn write(text: &AttributedText) -> String {
+    let mut out = String::from("<meta charset=\"utf-8\">");
+    // Whether a paragraph with visible content is open.
+    let mut open = false;
+    let mut space = true;
+    // A trailing newline ends the last paragraph rather than opening one.
+    let end = text.text().strip_suffix('\n').unwrap_or(text.text()).len();
+    let mut offset = 0;
+    for (run, style) in text.runs() {
+        let run = &run[..end.saturating_sub(offset).min(run.len())];
+        offset += run.len();
+        for (idx, segment) in run.split('\n').enumerate() {
+            if idx > 0 {
+                // The newline belongs to the paragraph it ends.
+                if !open {
+                    out.push_str(paragraph(style.alignment));
+                    out.push_str("<br>");
+                }
+                out.push_str("</div>");
+                open = false;
+            }
+            if segment.is_empty() {
+                continue;
+            }
+            if !open {
+                out.push_str(paragraph(style.alignment));
+                open = true;
+                space = true;
+            }
+            let (open, close) = style_tags(style);
+            out.push_str(&open);
+            for ch in segment.chars() {
+                match ch {
+                    // Keep runs of spaces from collapsing.
+                    ' ' if space => out.push_str("&nbsp;"),
+                    '\u{a0}' => out.push_str("&nbsp;"),
+                    '\t' => out.push_str("<span style=\"white-space: pre\">\t</span>"),
+                    _ => out.push_str(&escape(ch.encode_utf8(&mut [0; 4]))),
+                }
+                space = ch == ' ';
+            }
+            out.push_str(&close);
+        }
+    }
+    if !open {
+        out.push_str("<div><br>");
+    }
+    out.push_str("</div>");
+    out
+}
+
+fn paragraph(alignment: Alignment) -> &'static str {
+    match alignment {
+        Alignment::Left => "<div>",
+        Alignment::Center => "<div style=\"text-align: center\">",
+        Alignment::Right => "<div style=\"text-align: right\">",
+        Alignment::Justified => "<div style=\"text-align: justify\">",
+    }
+}
+
+fn style_tags(style: &RunStyle) -> (String, String) {
+    let mut css = Vec::new();
+    if let Some(font) = &style.font {
+        css.push(format!("font-family: '{}'", font.replace(['\'', '"'], "")));
+    }
+    if let Some(size) = style.size {
+        css.push(format!("font-size: {}", css::format_font_size(size)));
+    }
+    if let Some(color) = style.color {
+        css.push(format!("color: {}", css::format_color(color)));
+    }
+    if let Some(background) = style.background {
+        css.push(format!(
+            "background-color: {}",
+            css::format_color(background)
+        ));
+    }
+
+    let mut open = String::new();
+    let mut close = String::new();
+    if let Some(link) = style.link.as_ref().filter(|link| is_safe_url(link)) {
+        open = format!("<a href=\"{}\">", escape(link));
+        close = "</a>".to_string();
+    }
+    if !css.is_empty() {
+        open.push_str(&format!("<span style=\"{}\">", escape(&css.join("; "))));
+        close.insert_str(0, "</span>");
+    }
+    for (on, tag) in [
+        (style.bold, "b"),
+        (style.italic, "i"),
+        (style.underline, "u"),
+        (style.strikethrough, "s"),
+    ] {
+        if on {
+            open.push_str(&format!("<{tag}>"));
+            close.insert_str(0, &format!("</{tag}>"));
+        }
+    }
+    (open, close)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start(name: &str, attrs: &[(&str, &str)]) -> Token {
+        Token::Start {
+            name: name.into(),
+            attrs: attrs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            self_closing: VOID.contains(&name),
+        }
+    }
+
+    fn end(name: &str) -> Token {
+        Token::End { name: name.into() }
+    }
+
+    fn text(text: &str) -> Token {
+        Token::Text(text.into())
+    }
+
+    #[test]
+    fn tags_and_attributes() {
+        let tokens = tokenize(
+            r#"<!DOCTYPE html><P CLASS="a &amp; b" data-x='1' hidden>Hi<br/>there</p><!-- c -->"#,
+        );
+        assert_eq!(
+            tokens,
+            vec![
+                start("p", &[("class", "a & b"), ("data-x", "1"), ("hidden", "")]),
+                text("Hi"),
+                start("br", &[]),
+                text("there"),
+                end("p"),
+            ]
+        );
+        assert_eq!(tokens[0].attr("class"), Some("a & b"));
+    }
+
+    #[test]
+    fn raw_text() {
+        let tokens = tokenize("<script>if (a < b) { x = '</p>'; }</SCRIPT>after");
+        assert_eq!(
+            tokens,
+            vec![
+                start("script", &[]),
+                text("if (a < b) { x = '</p>'; }"),
+                end("script"),
+                text("after"),
+            ]
+        );
+    }
+
+    #[test]
+    fn stray_brackets() {
+        assert_eq!(tokenize("1 < 2 <> 3 <a"), vec![text("1 < 2 <> 3 <a")]);
+    }
+
+    #[test]
+    fn unclosed_markup() {
+        for unclosed in ["<a ", "<!--", "<!", "</a x='"] {
+            let html = unclosed.repeat(20_000);
+            assert_eq!(tokenize(&html), vec![text(&html)], "{unclosed}");
+        }
+        assert_eq!(
+            tokenize("<!-- x <b>y</b>"),
+            vec![text("<!-- x "), start("b", &[]), text("y"), end("b")]
+        );
+        let html = format!("{}<b>y</b>", "<!--".repeat(20_000));
+        assert_eq!(tokenize(&html).len(), 4);
+    }
+
+    #[test]
+    fn entities() {
+        assert_eq!(
+            decode_entities("&lt;&#65;&#x42;&nbsp;&unknown; & &hellip;&#0;"),
+            "<AB\u{a0}&unknown; & …\u{fffd}"
+        );
+    }
+
+    #[test]
+    fn write_paragraphs() {
+        let mut text = AttributedText::from("a  <b>\n\n");
+        text.push_str(
+            " x\ty\n",
+            &RunStyle {
+                italic: true,
+                font: Some("Menlo".into()),
+                ..RunStyle::default()
+            },
+        );
+        assert_eq!(
+            write(&text),
+            "<meta charset=\"utf-8\"><div>a &nbsp;&lt;b&gt;</div><div><br></div>\
+             <div><span style=\"font-family: 'Menlo'\"><i>&nbsp;x<span style=\"white-space: pre\">\t</span>y</i></span></div>"
+        );
+        assert_eq!(
+            write(&AttributedText::new()),
+            "<meta charset=\"utf-8\"><div><br></div>"
+        );
+    }
+
+    #[test]
+    fn write_links() {
+        let link = |href: &str| RunStyle {
+            link: Some(href.into()),
+            ..RunStyle::default()
+        };
+        let mut text = AttributedText::new();
+        text.push_str("ok", &link("https://x.com/?a&b"));
+        text.push_str(" bad", &link(" JavaScript:alert(1)"));
+        text.push_str(" data", &link("data:text/html,<b>"));
+        assert_eq!(
+            write(&text),
+            "<meta charset=\"utf-8\"><div><a href=\"https://x.com/?a&amp;b\">ok</a> bad data</div>"
+        );
+        assert_eq!(
+            crate::convert::rtf_to_html(
+                r#"{\rtf1{\field{\*\fldinst HYPERLINK "javascript:alert(1)"}{\fldrslt click}}}"#
+            ),
+            "<meta charset=\"utf-8\"><div>click</div>"
+        );
+    }
+}
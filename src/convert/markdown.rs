@@ -0,0 +1,1732 @@
+//! Conversion between CommonMark and HTML for the syntax people actually
+//! copy: headings, paragraphs, block quotes, lists, code, GFM tables, links,
+//! images and emphasis. Raw HTML in Markdown is escaped rather than passed
+//! through.
+
+use super::css::{declarations, parse_font_family, MONOSPACE};
+use super::html::{decode_entities, escape, is_safe_url, tokenize, Token, VOID};
+use super::text::{BLOCK, HIDDEN, PARAGRAPH};
+use super::Alignment;
+
+pub fn markdown_to_html(markdown: &str) -> String {
+    let lines: Vec<String> = markdown.lines().map(expand_tabs).collect();
+    let mut out = String::new();
+    render_blocks(&parse_blocks(&lines, 0), false, &mut out);
+    out
+}
+
+#[derive(Debug, PartialEq)]
+enum Block {
+    Heading(usize, String),
+    Paragraph(String),
+    Code {
+        lang: Option<String>,
+        text: String,
+    },
+    Quote(Vec<Block>),
+    List {
+        start: Option<u64>,
+        loose: bool,
+        items: Vec<Vec<Block>>,
+    },
+    Rule,
+    Table {
+        alignments: Vec<Option<Alignment>>,
+        header: Vec<String>,
+        rows: Vec<Vec<String>>,
+    },
+}
+
+/// Replace leading tabs with spaces up to the next multiple of four.
+fn expand_tabs(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    for ch in chars.by_ref() {
+        match ch {
+            '\t' => out.push_str(&" ".repeat(4 - out.len() % 4)),
+            ' ' => out.push(' '),
+            _ => {
+                out.push(ch);
+                break;
+            }
+        }
+    }
+    out.extend(chars);
+    out
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// `line` with up to `n` leading spaces removed.
+fn strip_indent(line: &str, n: usize) -> String {
+    line[indent(line).min(n)..].to_string()
+}
+
+/// An opening code fence: its character, length and info string.
+fn fence(rest: &str) -> Option<(char, usize, &str)> {
+    let ch = rest.chars().next().filter(|ch| *ch == '`' || *ch == '~')?;
+    let len = rest.len() - rest.trim_start_matches(ch).len();
+    let info = rest[len..].trim();
+    (len >= 3 && !(ch == '`' && info.contains('`'))).then_some((ch, len, info))
+}
+
+fn is_closing_fence(line: &str, ch: char, len: usize) -> bool {
+    let rest = line.trim();
+    indent(line) < 4 && rest.len() >= len && rest.chars().all(|c| c == ch)
+}
+
+fn atx_heading(rest: &str) -> Option<(usize, &str)> {
+    let level = rest.len() - rest.trim_start_matches('#').len();
+    let after = &rest[level..];
+    if !(1..=6).contains(&level) || !(after.is_empty() || after.starts_with(' ')) {
+        return None;
+    }
+    let text = after.trim();
+    // Drop a closing sequence of `#`s.
+    let stripped = text.trim_end_matches('#');
+    let text = if stripped.is_empty() || stripped.ends_with(' ') {
+        stripped.trim_end()
+    } else {
+        text
+    };
+    Some((level, text))
+}
+
+fn is_rule(rest: &str) -> bool {
+    let Some(ch) = rest
+        .chars()
+        .next()
+        .filter(|ch| matches!(ch, '-' | '*' | '_'))
+    else {
+        return false;
+    };
+    rest.chars().all(|c| c == ch || c == ' ') && rest.chars().filter(|c| *c == ch).count() >= 3
+}
+
+/// `=` or `-` underline turning the paragraph above into a heading.
+fn setext_level(line: &str) -> Option<usize> {
+    let rest = line.trim();
+    if indent(line) >= 4 || rest.is_empty() {
+        return None;
+    }
+    if rest.chars().all(|c| c == '=') {
+        Some(1)
+    } else if rest.chars().all(|c| c == '-') {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Marker {
+    /// The number and delimiter of an ordered item, or the bullet.
+    number: Option<u64>,
+    delimiter: char,
+    /// Column where the item's content starts.
+    width: usize,
+    empty: bool,
+}
+
+impl Marker {
+    fn same_list(&self, other: &Marker) -> bool {
+        self.number.is_some() == other.number.is_some() && self.delimiter == other.delimiter
+    }
+}
+
+fn list_marker(line: &str) -> Option<Marker> {
+    let ind = indent(line);
+    if ind >= 4 {
+        return None;
+    }
+    let rest = &line[ind..];
+    let (number, delimiter, len) = match rest.chars().next()? {
+        ch @ ('-' | '*' | '+') => (None, ch, 1),
+        _ => {
+            let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            let delimiter = rest[digits..].chars().next()?;
+            if !(1..=9).contains(&digits) || !matches!(delimiter, '.' | ')') {
+                return None;
+            }
+            (Some(rest[..digits].parse().ok()?), delimiter, digits + 1)
+        }
+    };
+    let after = &rest[len..];
+    if after.trim().is_empty() {
+        return Some(Marker {
+            number,
+            delimiter,
+            width: ind + len + 1,
+            empty: true,
+        });
+    }
+    let spaces = indent(after);
+    if spaces == 0 {
+        return None;
+    }
+    // More than four spaces start an indented code block inside the item.
+    let spaces = if spaces > 4 { 1 } else { spaces };
+    Some(Marker {
+        number,
+        delimiter,
+        width: ind + len + spaces,
+        empty: false,
+    })
+}
+
+/// Whether `line` starts a block that ends a paragraph.
+fn interrupts_paragraph(line: &str) -> bool {
+    if indent(line) >= 4 {
+        return false;
+    }
+    let rest = line.trim_start();
+    fence(rest).is_some()
+        || atx_heading(rest).is_some()
+        || is_rule(rest)
+        || rest.starts_with('>')
+        || list_marker(line)
+            .is_some_and(|marker| !marker.empty && marker.number.is_none_or(|n| n == 1))
+}
+
+/// Deepest nesting of quotes and lists `parse_blocks` builds, and of
+/// elements `parse_tree` keeps. Anything nested further is flattened into
+/// the innermost level, so the recursive walkers can't run out of stack.
+const MAX_DEPTH: usize = 64;
+
+fn parse_blocks(lines: &[String], depth: usize) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = &lines[i];
+        if is_blank(line) {
+            i += 1;
+            continue;
+        }
+        let ind = indent(line);
+        let rest = &line[ind..];
+
+        if ind >= 4 {
+            let mut code = Vec::new();
+            while i < lines.len() && (is_blank(&lines[i]) || indent(&lines[i]) >= 4) {
+                code.push(strip_indent(&lines[i], 4));
+                i += 1;
+            }
+            while code.last().is_some_and(|line| is_blank(line)) {
+                code.pop();
+            }
+            blocks.push(Block::Code {
+                lang: None,
+                text: code.iter().map(|line| format!("{line}\n")).collect(),
+            });
+            continue;
+        }
+
+        if let Some((ch, len, info)) = fence(rest) {
+            let lang = info.split_whitespace().next().map(decode_entities);
+            let mut text = String::new();
+            i += 1;
+            while i < lines.len() {
+                let line = &lines[i];
+                i += 1;
+                if is_closing_fence(line, ch, len) {
+                    break;
+                }
+                text.push_str(&strip_indent(line, ind));
+                text.push('\n');
+            }
+            blocks.push(Block::Code { lang, text });
+            continue;
+        }
+
+        if let Some((level, text)) = atx_heading(rest) {
+            blocks.push(Block::Heading(level, text.to_string()));
+            i += 1;
+            continue;
+        }
+
+        if is_rule(rest) {
+            blocks.push(Block::Rule);
+            i += 1;
+            continue;
+        }
+
+        if rest.starts_with('>') && depth < MAX_DEPTH {
+            let mut inner: Vec<String> = Vec::new();
+            while i < lines.len() {
+                let line = &lines[i];
+                let ind = indent(line);
+                if ind < 4 && line[ind..].starts_with('>') {
+                    let quoted = &line[ind + 1..];
+                    inner.push(quoted.strip_prefix(' ').unwrap_or(quoted).to_string());
+                } else if !is_blank(line)
+                    && inner.last().is_some_and(|last| !is_blank(last))
+                    && !interrupts_paragraph(line)
+                {
+                    // A lazy continuation of the quoted paragraph.
+                    inner.push(line.clone());
+                } else {
+                    break;
+                }
+                i += 1;
+            }
+            blocks.push(Block::Quote(parse_blocks(&inner, depth + 1)));
+            continue;
+        }
+
+        if let Some(marker) = list_marker(line).filter(|_| depth < MAX_DEPTH) {
+            let (list, end) = parse_list(lines, i, marker, depth);
+            blocks.push(list);
+            i = end;
+            continue;
+        }
+
+        if let Some((table, end)) = parse_table(lines, i) {
+            blocks.push(table);
+            i = end;
+            continue;
+        }
+
+        let mut text = vec![line.trim()];
+        let mut heading = None;
+        i += 1;
+        while i < lines.len() && !is_blank(&lines[i]) {
+            if let Some(level) = setext_level(&lines[i]) {
+                heading = Some(level);
+                i += 1;
+                break;
+            }
+            if interrupts_paragraph(&lines[i]) {
+                break;
+            }
+            text.push(lines[i].trim_start());
+            i += 1;
+        }
+        let text = text.join("\n");
+        blocks.push(match heading {
+            Some(level) => Block::Heading(level, text),
+            None => Block::Paragraph(text),
+        });
+    }
+    blocks
+}
+
+fn parse_list(lines: &[String], start: usize, first: Marker, depth: usize) -> (Block, usize) {
+    let mut items = Vec::new();
+    let mut loose = false;
+    let mut i = start;
+    let mut marker = first;
+    loop {
+        let line = &lines[i];
+        let mut item = vec![line.get(marker.width..).unwrap_or_default().to_string()];
+        i += 1;
+        while i < lines.len() {
+            let line = &lines[i];
+            if is_blank(line) {
+                item.push(String::new());
+            } else if indent(line) >= marker.width {
+                item.push(line[marker.width..].to_string());
+            } else if item.last().is_some_and(|last| !is_blank(last))
+                && !interrupts_paragraph(line)
+                && list_marker(line).is_none()
+            {
+                item.push(line.trim_start().to_string());
+            } else {
+                break;
+            }
+            i += 1;
+        }
+        let mut trailing_blank = false;
+        while item.last().is_some_and(|line| is_blank(line)) {
+            item.pop();
+            trailing_blank = true;
+        }
+        // A blank line between two blocks of the item makes the list loose.
+        loose |= item.iter().any(|line| is_blank(line));
+        items.push(parse_blocks(&item, depth + 1));
+
+        match lines.get(i).and_then(|line| list_marker(line)) {
+            Some(next) if next.same_list(&first) => {
+                loose |= trailing_blank;
+                marker = next;
+            }
+            _ => break,
+        }
+    }
+    let list = Block::List {
+        start: first.number,
+        loose,
+        items,
+    };
+    (list, i)
+}
+
+fn parse_table(lines: &[String], start: usize) -> Option<(Block, usize)> {
+    let header = &lines[start];
+    if !header.contains('|') {
+        return None;
+    }
+    let alignments = split_row(lines.get(start + 1)?)
+        .iter()
+        .map(|cell| {
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+            if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+                return None;
+            }
+            Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => Some(Alignment::Center),
+                (true, false) => Some(Alignment::Left),
+                (false, true) => Some(Alignment::Right),
+                (false, false) => None,
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let header = split_row(header);
+    if header.len() != alignments.len() {
+        return None;
+    }
+
+    let mut rows = Vec::new();
+    let mut i = start + 2;
+    while i < lines.len() && !is_blank(&lines[i]) && !interrupts_paragraph(&lines[i]) {
+        let mut row = split_row(&lines[i]);
+        row.resize(alignments.len(), String::new());
+        rows.push(row);
+        i += 1;
+    }
+    let table = Block::Table {
+        alignments,
+        header,
+        rows,
+    };
+    Some((table, i))
+}
+
+/// The trimmed cells of a table row; `\|` is a literal pipe.
+fn split_row(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = match line.strip_suffix('|') {
+        Some(stripped) if !stripped.ends_with('\\') => stripped,
+        _ => line,
+    };
+    let mut cells = vec![String::new()];
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' if chars.peek() == Some(&'|') => {
+                cells.last_mut().unwrap().push('|');
+                chars.next();
+            }
+            '|' => cells.push(String::new()),
+            _ => cells.last_mut().unwrap().push(ch),
+        }
+    }
+    cells.iter().map(|cell| cell.trim().to_string()).collect()
+}
+
+fn render_blocks(blocks: &[Block], tight: bool, out: &mut String) {
+    for block in blocks {
+        match block {
+            Block::Heading(level, text) => {
+                out.push_str(&format!("<h{level}>{}</h{level}>\n", inline(text)));
+            }
+            Block::Paragraph(text) if tight => {
+                out.push_str(&inline(text));
+                out.push('\n');
+            }
+            Block::Paragraph(text) => {
+                out.push_str(&format!("<p>{}</p>\n", inline(text)));
+            }
+            Block::Code { lang, text } => {
+                match lang {
+                    Some(lang) => {
+                        out.push_str(&format!("<pre><code class=\"language-{}\">", escape(lang)))
+                    }
+                    None => out.push_str("<pre><code>"),
+                }
+                out.push_str(&escape(text));
+                out.push_str("</code></pre>\n");
+            }
+            Block::Quote(blocks) => {
+                out.push_str("<blockquote>\n");
+                render_blocks(blocks, false, out);
+                out.push_str("</blockquote>\n");
+            }
+            Block::List {
+                start,
+                loose,
+                items,
+            } => {
+                let close = match start {
+                    None => {
+                        out.push_str("<ul>\n");
+                        "</ul>\n"
+                    }
+                    Some(1) => {
+                        out.push_str("<ol>\n");
+                        "</ol>\n"
+                    }
+                    Some(start) => {
+                        out.push_str(&format!("<ol start=\"{start}\">\n"));
+                        "</ol>\n"
+                    }
+                };
+                for item in items {
+                    let mut body = String::new();
+                    render_blocks(item, !loose, &mut body);
+                    match item.first() {
+                        Some(Block::Paragraph(_)) if !loose => {
+                            out.push_str(&format!("<li>{}</li>\n", body.trim_end_matches('\n')))
+                        }
+                        _ => out.push_str(&format!("<li>\n{body}</li>\n")),
+                    }
+                }
+                out.push_str(close);
+            }
+            Block::Rule => out.push_str("<hr>\n"),
+            Block::Table {
+                alignments,
+                header,
+                rows,
+            } => {
+                let row = |out: &mut String, cells: &[String], tag: &str| {
+                    out.push_str("<tr>\n");
+                    for (cell, alignment) in cells.iter().zip(alignments) {
+                        let align = match alignment {
+                            Some(Alignment::Left) => " align=\"left\"",
+                            Some(Alignment::Center) => " align=\"center\"",
+                            Some(Alignment::Right) => " align=\"right\"",
+                            _ => "",
+                        };
+                        out.push_str(&format!("<{tag}{align}>{}</{tag}>\n", inline(cell)));
+                    }
+                    out.push_str("</tr>\n");
+                };
+                out.push_str("<table>\n<thead>\n");
+                row(out, header, "th");
+                out.push_str("</thead>\n");
+                if !rows.is_empty() {
+                    out.push_str("<tbody>\n");
+                    for cells in rows {
+                        row(out, cells, "td");
+                    }
+                    out.push_str("</tbody>\n");
+                }
+                out.push_str("</table>\n");
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Node {
+    /// Literal text, not yet escaped.
+    Text(String),
+    Html(String),
+    Delimiter {
+        ch: char,
+        count: usize,
+        open: bool,
+        close: bool,
+    },
+}
+
+fn inline(text: &str) -> String {
+    nested_inline(text, 0)
+}
+
+/// `inline` for text `depth` links deep, which stops parsing links at
+/// `MAX_DEPTH`.
+fn nested_inline(text: &str, depth: usize) -> String {
+    let mut nodes = parse_inline(text, depth);
+    let tags = process_emphasis(&mut nodes);
+    let mut out = String::new();
+    for (node, (before, after)) in nodes.into_iter().zip(tags) {
+        out.extend(before);
+        match node {
+            Node::Text(text) => out.push_str(&escape(&decode_entities(&text))),
+            Node::Html(html) => out.push_str(&html),
+            Node::Delimiter { ch, count, .. } => out.extend(std::iter::repeat_n(ch, count)),
+        }
+        out.extend(after.into_iter().rev());
+    }
+    out
+}
+
+/// What `parse_inline` has learned about the text ahead, so that unclosed
+/// brackets and titles are looked for once rather than from every place a
+/// link might start.
+struct Lookahead {
+    /// The `]` matching each `[`, by index into the text.
+    closers: Vec<Option<usize>>,
+    /// Per character looked for, a range `start..end` known not to hold it,
+    /// ending at its next occurrence or, for `None`, the end of the text.
+    found: Vec<(char, usize, Option<usize>)>,
+}
+
+impl Lookahead {
+    fn new(chars: &[char]) -> Self {
+        let mut closers = vec![None; chars.len()];
+        let mut open = Vec::new();
+        let mut idx = 0;
+        while idx < chars.len() {
+            match chars[idx] {
+                '\\' => idx += 1,
+                '[' => open.push(idx),
+                ']' => {
+                    if let Some(start) = open.pop() {
+                        closers[start] = Some(idx);
+                    }
+                }
+                _ => {}
+            }
+            idx += 1;
+        }
+        Self {
+            closers,
+            found: Vec::new(),
+        }
+    }
+
+    /// The first `quote` from `from` on that isn't escaped by a backslash.
+    fn find(&mut self, chars: &[char], from: usize, quote: char) -> Option<usize> {
+        if let Some(&(_, start, end)) = self.found.iter().find(|(ch, ..)| *ch == quote) {
+            if start <= from && end.is_none_or(|end| from <= end) {
+                return end;
+            }
+        }
+        let end = (from..chars.len()).find(|&i| chars[i] == quote && chars[i - 1] != '\\');
+        self.found.retain(|(ch, ..)| *ch != quote);
+        self.found.push((quote, from, end));
+        end
+    }
+}
+
+fn is_punctuation(ch: char) -> bool {
+    ch.is_ascii_punctuation() || (!ch.is_alphanumeric() && !ch.is_whitespace() && !ch.is_ascii())
+}
+
+fn parse_inline(text: &str, depth: usize) -> Vec<Node> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut lookahead = Lookahead::new(&chars);
+    let mut nodes = Vec::new();
+    let mut buf = String::new();
+    let flush = |buf: &mut String, nodes: &mut Vec<Node>| {
+        if !buf.is_empty() {
+            nodes.push(Node::Text(std::mem::take(buf)));
+        }
+    };
+    let mut i = 0;
+    while i < chars.len() {
+        let ch = chars[i];
+        // Only counted for the delimiters, as scanning every run would make
+        // long runs of one character quadratic.
+        let run = || chars[i..].iter().take_while(|c| **c == ch).count();
+        match ch {
+            '\\' => match chars.get(i + 1) {
+                Some('\n') => {
+                    flush(&mut buf, &mut nodes);
+                    nodes.push(Node::Html("<br>\n".into()));
+                    i += 2;
+                }
+                Some(next) if next.is_ascii_punctuation() => {
+                    buf.push(*next);
+                    i += 2;
+                }
+                _ => {
+                    buf.push('\\');
+                    i += 1;
+                }
+            },
+            '`' => {
+                let run = run();
+                let close = (i + run..chars.len()).find(|&idx| {
+                    chars[idx] == '`'
+                        && (idx == 0 || chars[idx - 1] != '`')
+                        && chars[idx..].iter().take_while(|c| **c == '`').count() == run
+                });
+                match close {
+                    Some(close) => {
+                        let code: String = chars[i + run..close].iter().collect();
+                        let code = code.replace('\n', " ");
+                        let code = match code.strip_prefix(' ').and_then(|c| c.strip_suffix(' ')) {
+                            Some(inner) if !code.trim().is_empty() => inner.to_string(),
+                            _ => code,
+                        };
+                        flush(&mut buf, &mut nodes);
+                        nodes.push(Node::Html(format!("<code>{}</code>", escape(&code))));
+                        i = close + run;
+                    }
+                    None => {
+                        buf.push_str(&"`".repeat(run));
+                        i += run;
+                    }
+                }
+            }
+            '*' | '_' | '~' => {
+                let run = run();
+                let prev = if i == 0 { ' ' } else { chars[i - 1] };
+                let next = chars.get(i + run).copied().unwrap_or(' ');
+                let left = !next.is_whitespace()
+                    && (!is_punctuation(next) || prev.is_whitespace() || is_punctuation(prev));
+                let right = !prev.is_whitespace()
+                    && (!is_punctuation(prev) || next.is_whitespace() || is_punctuation(next));
+                let (open, close) = match ch {
+                    '_' => (
+                        left && (!right || is_punctuation(prev)),
+                        right && (!left || is_punctuation(next)),
+                    ),
+                    _ => (left, right),
+                };
+                if ch == '~' && run != 2 {
+                    buf.push_str(&"~".repeat(run));
+                } else {
+                    flush(&mut buf, &mut nodes);
+                    nodes.push(Node::Delimiter {
+                        ch,
+                        count: run,
+                        open,
+                        close,
+                    });
+                }
+                i += run;
+            }
+            '!' | '[' => {
+                let image = ch == '!';
+                let bracket = if image { i + 1 } else { i };
+                match chars
+                    .get(bracket)
+                    .filter(|c| **c == '[' && depth < MAX_DEPTH)
+                    .and_then(|_| link(&chars, bracket, image, &mut lookahead, depth))
+                {
+                    Some((html, end)) => {
+                        flush(&mut buf, &mut nodes);
+                        nodes.push(Node::Html(html));
+                        i = end;
+                    }
+                    None => {
+                        buf.push(ch);
+                        i += 1;
+                    }
+                }
+            }
+            '<' => match autolink(&chars, i) {
+                Some((html, end)) => {
+                    flush(&mut buf, &mut nodes);
+                    nodes.push(Node::Html(html));
+                    i = end;
+                }
+                None => {
+                    buf.push('<');
+                    i += 1;
+                }
+            },
+            '\n' => {
+                let hard = buf.ends_with("  ");
+                buf.truncate(buf.trim_end_matches(' ').len());
+                if hard {
+                    flush(&mut buf, &mut nodes);
+                    nodes.push(Node::Html("<br>\n".into()));
+                } else {
+                    buf.push('\n');
+                }
+                i += 1;
+            }
+            _ => {
+                buf.push(ch);
+                i += 1;
+            }
+        }
+    }
+    flush(&mut buf, &mut nodes);
+    nodes
+}
+
+/// Deepest parentheses nesting in a link destination, as in CommonMark's
+/// reference implementation.
+const MAX_PARENS: usize = 32;
+
+/// Parse `[text](dest "title")` or `![alt](src)` starting at the `[`,
+/// returning its HTML and the index after it.
+fn link(
+    chars: &[char],
+    open: usize,
+    image: bool,
+    lookahead: &mut Lookahead,
+    depth: usize,
+) -> Option<(String, usize)> {
+    let close = lookahead.closers[open]?;
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    let mut idx = close + 2;
+    let skip_spaces = |idx: &mut usize| {
+        while chars.get(*idx).is_some_and(|c| c.is_whitespace()) {
+            *idx += 1;
+        }
+    };
+    skip_spaces(&mut idx);
+
+    let mut dest = String::new();
+    if chars.get(idx) == Some(&'<') {
+        idx += 1;
+        while *chars.get(idx)? != '>' {
+            if matches!(chars[idx], '<' | '\n') {
+                return None;
+            }
+            dest.push(chars[idx]);
+            idx += 1;
+        }
+        idx += 1;
+    } else {
+        let mut parens = 0;
+        while let Some(&ch) = chars.get(idx) {
+            match ch {
+                '\\' if chars.get(idx + 1).is_some_and(char::is_ascii_punctuation) => {
+                    dest.push(chars[idx + 1]);
+                    idx += 2;
+                    continue;
+                }
+                '(' if parens == MAX_PARENS => return None,
+                '(' => parens += 1,
+                ')' if parens == 0 => break,
+                ')' => parens -= 1,
+                _ if ch.is_whitespace() => break,
+                _ => {}
+            }
+            dest.push(ch);
+            idx += 1;
+        }
+    }
+    skip_spaces(&mut idx);
+
+    let mut title = None;
+    if let Some(&quote) = chars.get(idx).filter(|c| matches!(c, '"' | '\'' | '(')) {
+        let end_quote = if quote == '(' { ')' } else { quote };
+        let start = idx + 1;
+        let end = lookahead.find(chars, start, end_quote)?;
+        title = Some(chars[start..end].iter().collect::<String>());
+        idx = end + 1;
+        skip_spaces(&mut idx);
+    }
+    if chars.get(idx) != Some(&')') {
+        return None;
+    }
+
+    let label: String = chars[open + 1..close].iter().collect();
+    let dest = decode_entities(&dest);
+    if !is_safe_url(&dest) {
+        // Keep the text, drop the link.
+        let text = if image {
+            escape(&decode_entities(&label))
+        } else {
+            nested_inline(&label, depth + 1)
+        };
+        return Some((text, idx + 1));
+    }
+    let dest = escape(&dest);
+    let title = title
+        .map(|title| format!(" title=\"{}\"", escape(&decode_entities(&title))))
+        .unwrap_or_default();
+    let html = if image {
+        let alt = escape(&decode_entities(&label));
+        format!("<img src=\"{dest}\" alt=\"{alt}\"{title}>")
+    } else {
+        format!(
+            "<a href=\"{dest}\"{title}>{}</a>",
+            nested_inline(&label, depth + 1)
+        )
+    };
+    Some((html, idx + 1))
+}
+
+/// Parse `<scheme:...>` or `<user@host>` starting at the `<`.
+fn autolink(chars: &[char], open: usize) -> Option<(String, usize)> {
+    // Stopping at the next `<` keeps unclosed ones from being rescanned.
+    let len = chars[open + 1..]
+        .iter()
+        .position(|c| matches!(c, '>' | '<') || c.is_whitespace())?;
+    if chars[open + 1 + len] != '>' {
+        return None;
+    }
+    let target: String = chars[open + 1..open + 1 + len].iter().collect();
+    if target.is_empty() {
+        return None;
+    }
+    let scheme = target.split(':').next().unwrap_or_default();
+    let href = if target.contains(':')
+        && (2..=32).contains(&scheme.len())
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '.' | '-'))
+    {
+        target.clone()
+    } else if target.contains('@') && !target.contains(':') {
+        format!("mailto:{target}")
+    } else {
+        return None;
+    };
+    if !is_safe_url(&href) {
+        return None;
+    }
+    let html = format!("<a href=\"{}\">{}</a>", escape(&href), escape(&target));
+    Some((html, open + len + 2))
+}
+
+/// Pair up `*`, `_` and `~~` delimiters into `<em>`, `<strong>` and `<del>`,
+/// returning the tags to put before and after each node; those after come
+/// innermost first.
+fn process_emphasis(nodes: &mut [Node]) -> Vec<(Vec<&'static str>, Vec<&'static str>)> {
+    let mut tags = vec![(Vec::new(), Vec::new()); nodes.len()];
+    // Delimiters that may still open a pair, innermost last.
+    let mut openers: Vec<usize> = Vec::new();
+    // Per delimiter character, how many of `openers` are known not to match
+    // it, as CommonMark's openers bottom, so failed searches aren't repeated.
+    let mut bottom = [0; 3];
+    for closer in 0..nodes.len() {
+        let Node::Delimiter {
+            ch, open, close, ..
+        } = nodes[closer]
+        else {
+            continue;
+        };
+        let slot = match ch {
+            '*' => 0,
+            '_' => 1,
+            _ => 2,
+        };
+        while close && delimiter_count(&nodes[closer]) > 0 {
+            let searched = bottom[slot].min(openers.len());
+            let found = openers[searched..]
+                .iter()
+                .rposition(|&idx| matches!(nodes[idx], Node::Delimiter { ch: c, .. } if c == ch));
+            let Some(at) = found.map(|at| searched + at) else {
+                bottom[slot] = openers.len();
+                break;
+            };
+            let opener = openers[at];
+            let used =
+                if delimiter_count(&nodes[opener]) >= 2 && delimiter_count(&nodes[closer]) >= 2 {
+                    2
+                } else {
+                    1
+                };
+            let (open_tag, close_tag) = match (ch, used) {
+                ('~', _) => ("<del>", "</del>"),
+                (_, 2) => ("<strong>", "</strong>"),
+                _ => ("<em>", "</em>"),
+            };
+            for idx in [opener, closer] {
+                if let Node::Delimiter { count, .. } = &mut nodes[idx] {
+                    *count -= used;
+                }
+            }
+            tags[opener].1.push(open_tag);
+            tags[closer].0.push(close_tag);
+            // Delimiters inside the pair can no longer match anything outside.
+            openers.truncate(at + 1);
+            if delimiter_count(&nodes[opener]) == 0 {
+                openers.pop();
+            }
+        }
+        if open && delimiter_count(&nodes[closer]) > 0 {
+            openers.push(closer);
+        }
+    }
+    tags
+}
+
+fn delimiter_count(node: &Node) -> usize {
+    match node {
+        Node::Delimiter { count, .. } => *count,
+        _ => 0,
+    }
+}
+
+/// Convert HTML to CommonMark, with GFM tables and strikethrough.
+///
+/// Headings, paragraphs, lists, block quotes, code, tables, links, images
+/// and emphasis map to their Markdown syntax; other elements contribute
+/// their text. Characters Markdown would read as syntax are escaped.
+pub fn html_to_markdown(html: &str) -> String {
+    let mut blocks = Vec::new();
+    write_blocks(&parse_tree(html), &mut blocks);
+    blocks.join("\n\n")
+}
+
+#[derive(Debug)]
+enum Element {
+    Node(Token, Vec<Element>),
+    Text(String),
+}
+
+fn tag_name(token: &Token) -> &str {
+    match token {
+        Token::Start { name, .. } | Token::End { name } => name,
+        Token::Text(_) => "",
+    }
+}
+
+/// Elements closed by the start of `name` when they are the innermost open
+/// one, as browsers do for `<li>one<li>two`.
+fn implied_ends(name: &str) -> &'static [&'static str] {
+    match name {
+        "li" => &["li", "p"],
+        "dt" | "dd" => &["dt", "dd", "p"],
+        "td" | "th" => &["td", "th"],
+        "tr" => &["tr", "td", "th"],
+        "tbody" | "tfoot" => &["thead", "tbody", "tr", "td", "th"],
+        _ if BLOCK.contains(&name) || PARAGRAPH.contains(&name) => &["p"],
+        _ => &[],
+    }
+}
+
+/// Build a tree from the token stream, closing unclosed elements the way a
+/// forgiving reader would.
+fn parse_tree(html: &str) -> Vec<Element> {
+    let mut root = Vec::new();
+    let mut open: Vec<(Token, Vec<Element>)> = Vec::new();
+    // Names of the elements dropped for being nested too deep.
+    let mut flattened: Vec<String> = Vec::new();
+    let close = |open: &mut Vec<(Token, Vec<Element>)>, root: &mut Vec<Element>| {
+        if let Some((token, children)) = open.pop() {
+            let node = Element::Node(token, children);
+            match open.last_mut() {
+                Some((_, siblings)) => siblings.push(node),
+                None => root.push(node),
+            }
+        }
+    };
+    for token in tokenize(html) {
+        match &token {
+            Token::Start {
+                name, self_closing, ..
+            } => {
+                let ends = implied_ends(name);
+                while open
+                    .last()
+                    .is_some_and(|(open, _)| ends.contains(&tag_name(open)))
+                {
+                    close(&mut open, &mut root);
+                }
+                if *self_closing || VOID.contains(&name.as_str()) {
+                    open.push((token, Vec::new()));
+                    close(&mut open, &mut root);
+                } else if open.len() >= MAX_DEPTH {
+                    flattened.push(name.clone());
+                } else {
+                    open.push((token, Vec::new()));
+                }
+            }
+            Token::End { name } => {
+                if flattened.last() == Some(name) {
+                    flattened.pop();
+                } else if let Some(idx) = open.iter().rposition(|(open, _)| tag_name(open) == name)
+                {
+                    while open.len() > idx {
+                        close(&mut open, &mut root);
+                    }
+                }
+            }
+            Token::Text(text) => match open.last_mut() {
+                Some((_, children)) => children.push(Element::Text(text.clone())),
+                None => root.push(Element::Text(text.clone())),
+            },
+        }
+    }
+    while !open.is_empty() {
+        close(&mut open, &mut root);
+    }
+    root
+}
+
+fn is_block(name: &str) -> bool {
+    BLOCK.contains(&name) || PARAGRAPH.contains(&name) || matches!(name, "html" | "body" | "center")
+}
+
+/// Append the Markdown blocks for `elements` to `out`, gathering runs of
+/// inline content into paragraphs.
+fn write_blocks(elements: &[Element], out: &mut Vec<String>) {
+    let mut paragraph = String::new();
+    for element in elements {
+        match element {
+            Element::Node(token, children) if is_block(tag_name(token)) => {
+                finish_paragraph(&mut paragraph, out);
+                write_block(token, children, out);
+            }
+            _ => write_inline(element, &mut paragraph),
+        }
+    }
+    finish_paragraph(&mut paragraph, out);
+}
+
+fn finish_paragraph(paragraph: &mut String, out: &mut Vec<String>) {
+    let text = paragraph.trim_matches(|c: char| c.is_ascii_whitespace());
+    let text = text.trim_end_matches('\\').trim_end();
+    if !text.is_empty() {
+        out.push(
+            text.lines()
+                .map(escape_line_start)
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+    }
+    paragraph.clear();
+}
+
+fn write_block(token: &Token, children: &[Element], out: &mut Vec<String>) {
+    match tag_name(token) {
+        name @ ("h1" | "h2" | "h3" | "h4" | "h5" | "h6") => {
+            let level = name[1..].parse().unwrap_or(1);
+            let text = inline_text(children).replace("\\\n", " ");
+            if !text.is_empty() {
+                out.push(format!("{} {}", "#".repeat(level), text));
+            }
+        }
+        "pre" => out.push(code_block(token, children)),
+        "blockquote" => {
+            let mut blocks = Vec::new();
+            write_blocks(children, &mut blocks);
+            let quoted = blocks.join("\n\n");
+            if !quoted.is_empty() {
+                let lines: Vec<_> = quoted
+                    .lines()
+                    .map(|line| match line {
+                        "" => ">".to_string(),
+                        _ => format!("> {line}"),
+                    })
+                    .collect();
+                out.push(lines.join("\n"));
+            }
+        }
+        name @ ("ul" | "ol") => {
+            let list = list(token, name == "ol", children);
+            if !list.is_empty() {
+                out.push(list);
+            }
+        }
+        "table" => {
+            let table = table(children);
+            if !table.is_empty() {
+                out.push(table);
+            }
+        }
+        "hr" => out.push("---".to_string()),
+        name if HIDDEN.contains(&name) => {}
+        _ => write_blocks(children, out),
+    }
+}
+
+fn code_block(token: &Token, children: &[Element]) -> String {
+    let mut code = String::new();
+    raw_text(children, &mut code);
+    let code = code
+        .strip_prefix('\n')
+        .unwrap_or(&code)
+        .trim_end_matches('\n');
+    // The language is named by a class on `<pre>` or its `<code>`.
+    let class_of = |token: &Token| {
+        token.attr("class").and_then(|class| {
+            class.split_ascii_whitespace().find_map(|class| {
+                class
+                    .strip_prefix("language-")
+                    .or_else(|| class.strip_prefix("lang-"))
+                    .map(str::to_string)
+            })
+        })
+    };
+    let lang = class_of(token)
+        .or_else(|| {
+            children.iter().find_map(|child| match child {
+                Element::Node(token, _) if tag_name(token) == "code" => class_of(token),
+                _ => None,
+            })
+        })
+        .unwrap_or_default();
+    let fence = "`".repeat(longest_run(code, '`').max(2) + 1);
+    format!("{fence}{lang}\n{code}\n{fence}")
+}
+
+/// All the text under `elements`, with `<br>` as a newline.
+fn raw_text(elements: &[Element], out: &mut String) {
+    for element in elements {
+        match element {
+            Element::Text(text) => out.push_str(text),
+            Element::Node(token, _) if tag_name(token) == "br" => out.push('\n'),
+            Element::Node(_, children) => raw_text(children, out),
+        }
+    }
+}
+
+fn longest_run(text: &str, ch: char) -> usize {
+    text.split(|c| c != ch).map(str::len).max().unwrap_or(0)
+}
+
+fn list(token: &Token, ordered: bool, children: &[Element]) -> String {
+    let mut items: Vec<Vec<String>> = Vec::new();
+    for child in children {
+        match child {
+            Element::Node(token, children) if tag_name(token) == "li" => {
+                let mut blocks = Vec::new();
+                write_blocks(children, &mut blocks);
+                items.push(blocks);
+            }
+            // Stray content, such as a list nested directly in a list,
+            // belongs to the item before it.
+            Element::Text(text) if text.trim().is_empty() => {}
+            _ => {
+                let mut blocks = Vec::new();
+                write_blocks(std::slice::from_ref(child), &mut blocks);
+                match items.last_mut() {
+                    Some(item) => item.extend(blocks),
+                    None => items.push(blocks),
+                }
+            }
+        }
+    }
+
+    let mut number: u64 = token
+        .attr("start")
+        .and_then(|start| start.trim().parse().ok())
+        .unwrap_or(1);
+    let loose = items
+        .iter()
+        .any(|blocks| blocks.iter().filter(|block| !is_list(block)).count() > 1);
+    let mut out = Vec::new();
+    for blocks in items {
+        let marker = if ordered {
+            let marker = format!("{number}. ");
+            number = number.saturating_add(1);
+            marker
+        } else {
+            "- ".to_string()
+        };
+        let mut body = String::new();
+        for (idx, block) in blocks.iter().enumerate() {
+            if idx > 0 {
+                body.push_str(if is_list(block) && !loose {
+                    "\n"
+                } else {
+                    "\n\n"
+                });
+            }
+            body.push_str(block);
+        }
+        let indent = " ".repeat(marker.len());
+        let lines: Vec<_> = body
+            .lines()
+            .enumerate()
+            .map(|(idx, line)| match (idx, line) {
+                (0, _) => format!("{marker}{line}"),
+                (_, "") => String::new(),
+                _ => format!("{indent}{line}"),
+            })
+            .collect();
+        if lines.is_empty() {
+            out.push(marker.trim_end().to_string());
+        } else {
+            out.push(lines.join("\n"));
+        }
+    }
+    out.join(if loose { "\n\n" } else { "\n" })
+}
+
+/// Whether a block written by `write_blocks` is a list. Paragraphs that
+/// would look like one have their marker escaped.
+fn is_list(block: &str) -> bool {
+    let digits = block.len() - block.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    block.starts_with("- ") || block == "-" || (digits > 0 && block[digits..].starts_with(". "))
+}
+
+fn table(children: &[Element]) -> String {
+    let mut rows = Vec::new();
+    collect_rows(children, &mut rows);
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 {
+        return String::new();
+    }
+    let line = |cells: Vec<String>| format!("| {} |", cells.join(" | "));
+    let mut lines = Vec::new();
+    for (idx, row) in rows.into_iter().enumerate() {
+        let mut cells: Vec<_> = row.iter().map(|(text, _)| text.clone()).collect();
+        cells.resize(columns, String::new());
+        if idx == 0 {
+            let mut alignments: Vec<_> = row.iter().map(|(_, alignment)| *alignment).collect();
+            alignments.resize(columns, None);
+            lines.push(line(cells));
+            let delimiters = alignments
+                .into_iter()
+                .map(|alignment| match alignment {
+                    Some(Alignment::Left) => ":---",
+                    Some(Alignment::Center) => ":---:",
+                    Some(Alignment::Right) => "---:",
+                    _ => "---",
+                })
+                .map(str::to_string)
+                .collect();
+            lines.push(line(delimiters));
+        } else {
+            lines.push(line(cells));
+        }
+    }
+    lines.join("\n")
+}
+
+/// The cells of each row under `elements` with their alignment. Nested
+/// tables are flattened into their cell.
+fn collect_rows(elements: &[Element], rows: &mut Vec<Vec<(String, Option<Alignment>)>>) {
+    for element in elements {
+        let Element::Node(token, children) = element else {
+            continue;
+        };
+        match tag_name(token) {
+            "tr" => {
+                let mut row = Vec::new();
+                for cell in children {
+                    let Element::Node(token, children) = cell else {
+                        continue;
+                    };
+                    if !matches!(tag_name(token), "td" | "th") {
+                        continue;
+                    }
+                    let text = inline_text(children)
+                        .replace("\\\n", " ")
+                        .replace('\n', " ")
+                        .replace('|', "\\|");
+                    let alignment = token.attr("align").or_else(|| {
+                        token.attr("style").and_then(|style| {
+                            declarations(style)
+                                .find(|(property, _)| property == "text-align")
+                                .map(|(_, value)| value)
+                        })
+                    });
+                    let alignment = match alignment.map(str::to_ascii_lowercase).as_deref() {
+                        Some("left") => Some(Alignment::Left),
+                        Some("center") => Some(Alignment::Center),
+                        Some("right") => Some(Alignment::Right),
+                        _ => None,
+                    };
+                    let span = token
+                        .attr("colspan")
+                        .and_then(|span| span.trim().parse().ok())
+                        .unwrap_or(1usize)
+                        .clamp(1, 1000);
+                    row.push((text, alignment));
+                    row.extend(std::iter::repeat_n((String::new(), alignment), span - 1));
+                }
+                rows.push(row);
+            }
+            "table" => {}
+            _ => collect_rows(children, rows),
+        }
+    }
+}
+
+/// The inline Markdown for `elements`, trimmed.
+fn inline_text(elements: &[Element]) -> String {
+    let mut out = String::new();
+    for element in elements {
+        write_inline(element, &mut out);
+    }
+    out.trim_matches(|c: char| c.is_ascii_whitespace())
+        .trim_end_matches('\\')
+        .trim_end()
+        .to_string()
+}
+
+fn write_inline(element: &Element, out: &mut String) {
+    let (token, children) = match element {
+        Element::Text(text) => {
+            let mut collapsed = String::new();
+            if text.starts_with(|c: char| c.is_ascii_whitespace()) {
+                collapsed.push(' ');
+            }
+            let words: Vec<_> = text.split_ascii_whitespace().collect();
+            collapsed.push_str(&escape_inline(&words.join(" ")));
+            if !words.is_empty() && text.ends_with(|c: char| c.is_ascii_whitespace()) {
+                collapsed.push(' ');
+            }
+            push_text(out, &collapsed);
+            return;
+        }
+        Element::Node(token, children) => (token, children),
+    };
+    let name = tag_name(token);
+    match name {
+        _ if HIDDEN.contains(&name) => {}
+        "br" => {
+            out.truncate(out.trim_end_matches(' ').len());
+            if !out.is_empty() && !out.ends_with('\n') {
+                out.push_str("\\\n");
+            }
+        }
+        "img" => {
+            let Some(src) = token.attr("src").filter(|src| is_safe_url(src)) else {
+                return;
+            };
+            let alt = escape_inline(token.attr("alt").unwrap_or_default());
+            push_text(
+                out,
+                &format!("![{alt}]({}{})", destination(src), title(token)),
+            );
+        }
+        "a" => {
+            let text = inline_text(children);
+            match token
+                .attr("href")
+                .filter(|href| !href.trim().is_empty() && is_safe_url(href))
+            {
+                Some(href) if text.is_empty() => {
+                    push_text(out, &format!("<{}>", href.trim()));
+                }
+                Some(href) => {
+                    let close = format!("]({}{})", destination(href), title(token));
+                    wrap(out, children, "[", &close);
+                }
+                _ => push_text(out, &text),
+            }
+        }
+        "b" | "strong" if !style_says(token, "font-weight", &["normal", "400"]) => {
+            wrap(out, children, "**", "**")
+        }
+        "i" | "em" | "cite" | "dfn" | "var" => wrap(out, children, "*", "*"),
+        "s" | "strike" | "del" => wrap(out, children, "~~", "~~"),
+        "code" | "kbd" | "samp" | "tt" => code_span(out, children),
+        "span" | "font" if is_monospace(token) => code_span(out, children),
+        "span"
+            if style_says(
+                token,
+                "font-weight",
+                &["bold", "bolder", "600", "700", "800", "900"],
+            ) =>
+        {
+            wrap(out, children, "**", "**")
+        }
+        "span" if style_says(token, "font-style", &["italic", "oblique"]) => {
+            wrap(out, children, "*", "*")
+        }
+        _ => {
+            // Blocks nested in inline content, such as lists in a table
+            // cell, keep their words apart.
+            let block = is_block(name);
+            if block {
+                push_text(out, " ");
+            }
+            for child in children {
+                write_inline(child, out);
+            }
+            if block {
+                push_text(out, " ");
+            }
+        }
+    }
+}
+
+/// Append `text`, dropping its leading space where one is already owed.
+fn push_text(out: &mut String, text: &str) {
+    match text.strip_prefix(' ') {
+        Some(rest) if out.is_empty() || out.ends_with([' ', '\n']) => out.push_str(rest),
+        _ => out.push_str(text),
+    }
+}
+
+/// Append the inline content of `children` between `open` and `close`,
+/// keeping surrounding whitespace outside the markers.
+fn wrap(out: &mut String, children: &[Element], open: &str, close: &str) {
+    let mut inner = String::new();
+    for child in children {
+        write_inline(child, &mut inner);
+    }
+    let trimmed = inner.trim();
+    if trimmed.is_empty() {
+        if !inner.is_empty() {
+            push_text(out, " ");
+        }
+        return;
+    }
+    if inner.starts_with(' ') {
+        push_text(out, " ");
+    }
+    push_text(out, &format!("{open}{trimmed}{close}"));
+    if inner.ends_with(' ') {
+        push_text(out, " ");
+    }
+}
+
+fn code_span(out: &mut String, children: &[Element]) {
+    let mut code = String::new();
+    raw_text(children, &mut code);
+    let code = code.split_ascii_whitespace().collect::<Vec<_>>().join(" ");
+    if code.is_empty() {
+        return;
+    }
+    let fence = "`".repeat(longest_run(&code, '`') + 1);
+    let pad = if code.starts_with('`') || code.ends_with('`') {
+        " "
+    } else {
+        ""
+    };
+    push_text(out, &format!("{fence}{pad}{code}{pad}{fence}"));
+}
+
+/// A link destination, in angle brackets when it has spaces or brackets.
+fn destination(href: &str) -> String {
+    let href = href.trim();
+    if href.contains(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '<' | '>')) {
+        format!("<{}>", href.replace('<', "%3C").replace('>', "%3E"))
+    } else {
+        href.to_string()
+    }
+}
+
+fn title(token: &Token) -> String {
+    token
+        .attr("title")
+        .map(|title| format!(" \"{}\"", title.replace('"', "\\\"")))
+        .unwrap_or_default()
+}
+
+/// Whether the inline style of `token` sets `property` to one of `values`.
+fn style_says(token: &Token, property: &str, values: &[&str]) -> bool {
+    token.attr("style").is_some_and(|style| {
+        declarations(style).any(|(name, value)| {
+            name == property && values.contains(&value.to_ascii_lowercase().as_str())
+        })
+    })
+}
+
+/// Monospaced fonts, whose text reads as code.
+const MONOSPACED: &[&str] = &[
+    MONOSPACE,
+    "Courier New",
+    "Menlo",
+    "Monaco",
+    "Consolas",
+    "SF Mono",
+];
+
+fn is_monospace(token: &Token) -> bool {
+    let family = token
+        .attr("style")
+        .and_then(|style| {
+            declarations(style)
+                .find(|(property, _)| property == "font-family")
+                .and_then(|(_, value)| parse_font_family(value))
+        })
+        .or_else(|| token.attr("face").and_then(parse_font_family));
+    family.is_some_and(|family| {
+        MONOSPACED
+            .iter()
+            .any(|mono| mono.eq_ignore_ascii_case(&family))
+    })
+}
+
+pub(crate) fn escape_inline(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\\' | '*' | '_' | '`' | '[' | ']' | '<' => {
+                out.push('\\');
+                out.push(ch);
+            }
+            '\u{a0}' => out.push(' '),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+/// Escape what would start a heading, quote, list or rule at the start of
+/// a paragraph line.
+fn escape_line_start(line: &str) -> String {
+    if line.starts_with(['#', '>', '+', '-', '=', '~']) {
+        return format!("\\{line}");
+    }
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits > 0 && line[digits..].starts_with(['.', ')']) {
+        return format!("{}\\{}", &line[..digits], &line[digits..]);
+    }
+    line.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks() {
+        let md = "# Title #\n\nSome *text*\nover lines.\n\nSub\n---\n\n> quoted\nlazy\n\n***\n\n    indented\n";
+        assert_eq!(
+            markdown_to_html(md),
+            "<h1>Title</h1>\n<p>Some <em>text</em>\nover lines.</p>\n<h2>Sub</h2>\n\
+             <blockquote>\n<p>quoted\nlazy</p>\n</blockquote>\n<hr>\n<pre><code>indented\n</code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn code_blocks() {
+        let md = "```rust\nfn main() {\n    x < y\n}\n```\nafter\n\n~~~\nunclosed";
+        assert_eq!(
+            markdown_to_html(md),
+            "<pre><code class=\"language-rust\">fn main() {\n    x &lt; y\n}\n</code></pre>\n\
+             <p>after</p>\n<pre><code>unclosed\n</code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn lists() {
+        let md = "- one\n- two\n  1. nested\n  2. more\n- three\n\n3) a\n\n4) b\n";
+        assert_eq!(
+            markdown_to_html(md),
+            "<ul>\n<li>one</li>\n<li>two\n<ol>\n<li>nested</li>\n<li>more</li>\n</ol></li>\n<li>three</li>\n</ul>\n\
+             <ol start=\"3\">\n<li>\n<p>a</p>\n</li>\n<li>\n<p>b</p>\n</li>\n</ol>\n"
+        );
+    }
+
+    #[test]
+    fn tables() {
+        let md = "| Name | Qty |  Note |\n|:-----|----:|:-----:|\n| `a\\|b` | 3 |\n| **pear** | 10 | x | extra |\n\nafter";
+        assert_eq!(
+            markdown_to_html(md),
+            "<table>\n<thead>\n<tr>\n<th align=\"left\">Name</th>\n<th align=\"right\">Qty</th>\n<th align=\"center\">Note</th>\n</tr>\n</thead>\n\
+             <tbody>\n<tr>\n<td align=\"left\"><code>a|b</code></td>\n<td align=\"right\">3</td>\n<td align=\"center\"></td>\n</tr>\n\
+             <tr>\n<td align=\"left\"><strong>pear</strong></td>\n<td align=\"right\">10</td>\n<td align=\"center\">x</td>\n</tr>\n</tbody>\n</table>\n\
+             <p>after</p>\n"
+        );
+    }
+
+    #[test]
+    fn inlines() {
+        let cases = [
+            ("*em* **strong** ***both*** ~~gone~~", "<em>em</em> <strong>strong</strong> <em><strong>both</strong></em> <del>gone</del>"),
+            ("snake_case_name and _under_", "snake_case_name and <em>under</em>"),
+            ("2 * 3 * 4 and **unclosed", "2 * 3 * 4 and **unclosed"),
+            ("`` a ` b `` and `x`", "<code>a ` b</code> and <code>x</code>"),
+            (r"\*not\* &amp; <b> &copy;", "*not* &amp; &lt;b&gt; ©"),
+            ("[a *link*](http://x.com/a_(b) \"T\") ![pic](i.png)", "<a href=\"http://x.com/a_(b)\" title=\"T\">a <em>link</em></a> <img src=\"i.png\" alt=\"pic\">"),
+            ("<https://x.com?a=1&b=2> <me@x.com> [no link]", "<a href=\"https://x.com?a=1&amp;b=2\">https://x.com?a=1&amp;b=2</a> <a href=\"mailto:me@x.com\">me@x.com</a> [no link]"),
+            ("hard  \nbreak\\\nagain", "hard<br>\nbreak<br>\nagain"),
+        ];
+        for (md, html) in cases {
+            assert_eq!(inline(md), html, "{md}");
+        }
+    }
+
+    #[test]
+    fn unsafe_links() {
+        assert_eq!(
+            inline("[x](javascript:alert(1)) [y](<JavaScript\t:alert(1)>) ![z](data:text/html,hi)"),
+            "x y z"
+        );
+        assert_eq!(
+            inline("[a](vbscript:msgbox) <javascript:alert(1)> [b](/data:x)"),
+            "a &lt;javascript:alert(1)&gt; <a href=\"/data:x\">b</a>"
+        );
+        assert_eq!(
+            html_to_markdown(
+                "<a href=\" javascript:alert(1)\">x</a> <a href=\"vbscript:y\"></a>\
+                 <img src=\"data:image/png;base64,AA\" alt=\"z\"> <a href=\"a.html\">ok</a>"
+            ),
+            "x [ok](a.html)"
+        );
+    }
+
+    #[test]
+    fn from_html() {
+        let html = "<h1>Title</h1><p>Some <b>bold </b>and <i>snake_case</i>, <code>a`b</code>.</p>\
+                    <p># not a heading<br>1. not a list</p><hr>\
+                    <p><a href=\"https://x.com/a b\" title=\"T\">link <b>bold</b></a> <img src=\"i.png\" alt=\"pic\"></p>\
+                    <div><span style=\"font-family: Courier\">mono</span> <b style=\"font-weight: normal\">plain</b></div>\
+                    <script>hidden()</script>";
+        assert_eq!(
+            html_to_markdown(html),
+            "# Title\n\nSome **bold** and *snake\\_case*, ``a`b``.\n\n\\# not a heading\\\n1\\. not a list\n\n---\n\n\
+             [link **bold**](<https://x.com/a b> \"T\") ![pic](i.png)\n\n`mono` plain"
+        );
+    }
+
+    #[test]
+    fn lists_quotes_and_code_from_html() {
+        let html = "<ul><li>one<li>two<ul><li>nested</li></ul></li></ul><ol start=\"3\"><li>three</li><li>four</li></ol>\
+                    <blockquote><p>quote</p><ul><li>x</li></ul></blockquote>\
+                    <pre><code class=\"language-rust\">fn main() {\n    ```\n}</code></pre>";
+        assert_eq!(
+            html_to_markdown(html),
+            "- one\n- two\n  - nested\n\n3. three\n4. four\n\n> quote\n>\n> - x\n\n\
+             ````rust\nfn main() {\n    ```\n}\n````"
+        );
+        assert_eq!(
+            html_to_markdown("<ul><li><p>a</p><p>b</p></li><li>c</li></ul>"),
+            "- a\n\n  b\n\n- c"
+        );
+    }
+
+    #[test]
+    fn hostile_markdown() {
+        let html = markdown_to_html(&">".repeat(100_000));
+        assert_eq!(html.matches("<blockquote>").count(), MAX_DEPTH);
+        assert!(html.contains(&"&gt;".repeat(100_000 - MAX_DEPTH)));
+
+        let md: Vec<String> = (0..200).map(|i| format!("{}- x", "  ".repeat(i))).collect();
+        let html = markdown_to_html(&md.join("\n"));
+        assert_eq!(html.matches("<ul>").count(), MAX_DEPTH);
+        assert_eq!(html.matches('x').count(), 200);
+    }
+
+    #[test]
+    fn hostile_inline() {
+        let n = 20_000;
+        assert_eq!(inline(&"[a](".repeat(n)), "[a](".repeat(n));
+        assert_eq!(inline(&"<a".repeat(n)), "&lt;a".repeat(n));
+        assert_eq!(inline(&"[".repeat(n)), "[".repeat(n));
+        let html = inline(&format!("{}{}", "*a".repeat(n), "a*".repeat(n)));
+        assert_eq!(html.matches("<em>").count(), n);
+        assert_eq!(html.matches("</em>").count(), n);
+        let html = inline(&format!("{}x{}", "[".repeat(n), "](y)".repeat(n)));
+        assert_eq!(html.matches("<a ").count(), MAX_DEPTH);
+    }
+
+    #[test]
+    fn hostile_html() {
+        assert_eq!(
+            html_to_markdown("<ol start=\"18446744073709551615\"><li>a</li><li>b</li></ol>"),
+            "18446744073709551615. a\n18446744073709551615. b"
+        );
+        let depth = 100_000;
+        let html = format!(
+            "{}deep{}<p>after</p>",
+            "<div><blockquote>".repeat(depth),
+            "</blockquote></div>".repeat(depth)
+        );
+        let markdown = html_to_markdown(&html);
+        assert!(markdown.ends_with("deep\n\nafter"));
+        assert_eq!(markdown.matches('>').count(), MAX_DEPTH / 2);
+    }
+
+    #[test]
+    fn tables_from_html() {
+        let html = "<table><thead><tr><th>Name</th><th align=\"right\">Qty</th></tr></thead>\
+                    <tbody><tr><td>a|b</td><td>3</td><tr><td colspan=\"2\">wide<br>cell</td></tbody></table>";
+        assert_eq!(
+            html_to_markdown(html),
+            "| Name | Qty |\n| --- | ---: |\n| a\\|b | 3 |\n| wide cell |  |"
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let md =
+            "## Notes\n\nSome *em*, **strong** and `code` with [a link](https://example.com).\n\n\
+                  - one\n- two\n  1. nested\n\n> quoted\n\n```sh\necho hi\n```\n\n\
+                  | a | b |\n| :--- | ---: |\n| 1 | 2 |";
+        let html = markdown_to_html(md);
+        assert_eq!(html_to_markdown(&html), md);
+    }
+}
@@ -0,0 +1,292 @@
+//! Conversions between the rich text formats found on the pasteboard.
+
+mod attributed;
+mod css;
+mod html;
+mod markdown;
+mod rtf;
+mod table;
+mod text;
+
+pub use attributed::{Alignment, AttributedText, Color, RunStyle};
+pub use markdown::{html_to_markdown, markdown_to_html};
+pub use rtf::{rtf_to_html, rtf_to_text};
+pub use table::{tables_from_html, Table};
+pub use text::html_to_text;
+
+use crate::{ClipboardBackend, ClipboardError, Content, PasteBoard, Type};
+
+pub fn html_to_rtf(html: &str) -> String {
+    AttributedText::from_html(html).to_rtf()
+}
+
+/// When `items` holds only one of `Type::HTML` and `Type::RTF`, convert it
+/// and add the other.
+pub(crate) fn add_rich_text_counterpart(items: &mut Vec<(Type, Content)>) {
+    let find = |ty: Type| {
+        items
+            .iter()
+            .find(|(item, _)| *item == ty)
+            .and_then(|(_, content)| content.as_str())
+    };
+    let counterpart = match (find(Type::HTML), find(Type::RTF)) {
+        (Some(html), None) => (Type::RTF, html_to_rtf(html)),
+        (None, Some(rtf)) => (Type::HTML, rtf_to_html(rtf)),
+        _ => return,
+    };
+    items.push((counterpart.0, Content::String(counterpart.1.into())));
+}
+
+impl<B: ClipboardBackend> PasteBoard<B> {
+    /// Read text of `ty`, treating a missing or non-text representation as
+    /// absent.
+    pub(crate) fn get_text(&self, ty: Type) -> Result<Option<String>, ClipboardError> {
+        match self.board.get_contents(ty) {
+            Ok(content) => Ok(content.as_str().map(str::to_string)),
+            Err(ClipboardError::TypeUnavailable(_)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Publish `markdown` rendered as `Type::HTML` and `Type::RTF`, with the
+    /// Markdown source itself as `Type::String`, in one transaction.
+    pub fn write_markdown(&self, markdown: &str) -> Result<(), ClipboardError> {
+        let html = markdown_to_html(markdown);
+        let rtf = html_to_rtf(&html);
+        self.writer()
+            .add(
+                Type::HTML,
+                Content::String(format!("<meta charset=\"utf-8\">{html}").into()),
+            )
+            .add(Type::RTF, Content::String(rtf.into()))
+            .add(Type::String, Content::String(markdown.into()))
+            .commit()
+    }
+
+    /// The clipboard as attributed text, read from `Type::RTF`, `Type::HTML`
+    /// or plain `Type::String`, whichever comes first in that order.
+    pub fn get_attributed(&self) -> Result<AttributedText, ClipboardError> {
+        if let Some(rtf) = self.get_text(Type::RTF)? {
+            return Ok(AttributedText::from_rtf(&rtf));
+        }
+        if let Some(html) = self.get_text(Type::HTML)? {
+            return Ok(AttributedText::from_html(&html));
+        }
+        match self.get_text(Type::String)? {
+            Some(text) => Ok(text.into()),
+            None => Err(ClipboardError::TypeUnavailable(Type::RTF)),
+        }
+    }
+
+    /// The clipboard as Markdown, converted from `Type::HTML`, or from
+    /// `Type::RTF` by way of HTML.
+    pub fn get_markdown(&self) -> Result<String, ClipboardError> {
+        if let Some(html) = self.get_text(Type::HTML)? {
+            return Ok(html_to_markdown(&html));
+        }
+        match self.get_text(Type::RTF)? {
+            Some(rtf) => Ok(html_to_markdown(&rtf_to_html(&rtf))),
+            None => Err(ClipboardError::TypeUnavailable(Type::HTML)),
+        }
+    }
+
+    /// The clipboard as plain text, rendered from `Type::HTML` or `Type::RTF`
+    /// when the source app published no `Type::String`.
+    pub fn get_text_lossy(&self) -> Result<String, ClipboardError> {
+        if let Some(text) = self.get_text(Type::String)? {
+            return Ok(text);
+        }
+        if let Some(html) = self.get_text(Type::HTML)? {
+            return Ok(html_to_text(&html));
+        }
+        match self.get_text(Type::RTF)? {
+            Some(rtf) => Ok(rtf_to_text(&rtf)),
+            None => Err(ClipboardError::TypeUnavailable(Type::String)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemoryPasteBoard, WriteOptions};
+
+    #[test]
+    fn text_lossy() {
+        let board = PasteBoard::with_backend(MemoryPasteBoard::new());
+        assert!(matches!(
+            board.get_text_lossy(),
+            Err(ClipboardError::TypeUnavailable(Type::String))
+        ));
+
+        board
+            .write_contents(Content::String(r"{\rtf1 a\par b}".into()), Type::RTF)
+            .unwrap();
+        assert_eq!(board.get_text_lossy().unwrap(), "a\nb");
+
+        board
+            .write_contents(Content::String("<p>a</p><p>b</p>".into()), Type::HTML)
+            .unwrap();
+        assert_eq!(board.get_text_lossy().unwrap(), "a\n\nb");
+
+        board
+            .writer()
+            .add(Type::HTML, Content::String("<b>rich</b>".into()))
+            .add(Type::String, Content::String("plain".into()))
+            .commit()
+            .unwrap();
+        assert_eq!(board.get_text_lossy().unwrap(), "plain");
+    }
+
+    #[test]
+    fn html_rtf_round_trip() {
+        let html = "<p>Hello <b>bold</b> and <span style=\"color: #ff0000; font-size: 18px\">red</span></p><p><i>It&apos;s</i> <a href=\"x\">done</a></p>";
+        let rtf = html_to_rtf(html);
+        assert_eq!(rtf_to_text(&rtf), "Hello bold and red\n\nIt's done");
+        assert!(rtf.contains(r"{\b bold}"));
+        assert!(rtf.contains(r"{\colortbl;\red255\green0\blue0;}"));
+        assert!(rtf.contains(r"{\fs27\cf1 red\par"));
+
+        let html = rtf_to_html(&rtf);
+        assert_eq!(
+            html,
+            "<meta charset=\"utf-8\"><div>Hello <b>bold</b> and <span style=\"font-size: 13.5pt; color: #ff0000\">red</span></div><div><br></div><div><i>It's</i> <a href=\"x\">done</a></div>"
+        );
+        assert_eq!(
+            AttributedText::from_html(&html),
+            AttributedText::from_rtf(&rtf)
+        );
+    }
+
+    #[test]
+    fn rich_text_counterpart() {
+        let mut board = PasteBoard::with_backend(MemoryPasteBoard::new());
+        board
+            .write_contents(Content::String("<b>x</b>".into()), Type::HTML)
+            .unwrap();
+        assert_eq!(board.types().unwrap(), vec![Type::HTML]);
+
+        board.set_write_options(WriteOptions::default().rich_text_counterpart(true));
+        board
+            .write_contents(Content::String("<b>x</b>".into()), Type::HTML)
+            .unwrap();
+        assert_eq!(board.types().unwrap(), vec![Type::HTML, Type::RTF]);
+        let rtf = board.get_contents(Type::RTF, false).unwrap();
+        assert_eq!(
+            rtf.as_str(),
+            Some("{\\rtf1\\ansi\\ansicpg1252\\uc1\n{\\b x}}")
+        );
+
+        board
+            .writer()
+            .add(Type::RTF, Content::String(r"{\rtf1 a\par b}".into()))
+            .add(Type::String, Content::String("a b".into()))
+            .commit()
+            .unwrap();
+        assert_eq!(
+            board.types().unwrap(),
+            vec![Type::RTF, Type::String, Type::HTML]
+        );
+        let html = board.get_contents(Type::HTML, false).unwrap();
+        assert_eq!(
+            html.as_str(),
+            Some("<meta charset=\"utf-8\"><div>a</div><div>b</div>")
+        );
+
+        // Both given: nothing is converted.
+        board
+            .writer()
+            .add(Type::RTF, Content::String(r"{\rtf1 a}".into()))
+            .add(Type::HTML, Content::String("b".into()))
+            .commit()
+            .unwrap();
+        assert_eq!(board.get_text_lossy().unwrap(), "b");
+    }
+
+    #[test]
+    fn attributed() {
+        let board = PasteBoard::with_backend(MemoryPasteBoard::new());
+        assert!(matches!(
+            board.get_attributed(),
+            Err(ClipboardError::TypeUnavailable(Type::RTF))
+        ));
+        board
+            .write_contents(Content::String("plain".into()), Type::String)
+            .unwrap();
+        assert_eq!(
+            board.get_attributed().unwrap(),
+            AttributedText::from("plain")
+        );
+
+        let html = r#"<p style="text-align: center">Title</p><p>See <a href="https://example.com"><mark>this</mark></a></p>"#;
+        let text = AttributedText::from_html(html);
+        let runs: Vec<_> = text.runs().collect();
+        assert_eq!(runs[0].0, "Title\n\n");
+        assert_eq!(runs[0].1.alignment, Alignment::Center);
+        assert_eq!(runs[2].0, "this");
+        assert_eq!(runs[2].1.link.as_deref(), Some("https://example.com"));
+        assert_eq!(runs[2].1.background, Some(Color::new(255, 255, 0)));
+
+        // Attributed content is rendered to match each type it is written as.
+        board
+            .writer()
+            .add(Type::RTF, Content::Attributed(text.clone()))
+            .add(Type::HTML, Content::Attributed(text.clone()))
+            .add(Type::String, Content::Attributed(text.clone()))
+            .commit()
+            .unwrap();
+        assert_eq!(board.get_attributed().unwrap(), text);
+        let rtf = board.get_text(Type::RTF).unwrap().unwrap();
+        assert!(rtf.contains(r#"\pard\qc Title\par"#));
+        assert!(rtf.contains(
+            r#"{\field{\*\fldinst{HYPERLINK "https://example.com"}}{\fldrslt {\cb1 this}}}"#
+        ));
+        let html = board.get_text(Type::HTML).unwrap().unwrap();
+        assert_eq!(AttributedText::from_html(&html), text);
+        assert!(html
+            .starts_with(r#"<meta charset="utf-8"><div style="text-align: center">Title</div>"#));
+        assert_eq!(board.get_text_lossy().unwrap(), "Title\n\nSee this");
+    }
+
+    #[test]
+    fn markdown() {
+        let backend = MemoryPasteBoard::new();
+        let board = PasteBoard::with_backend(backend.clone());
+        let md = "# Notes\n\n- **bold** item\n- `code`\n\n| a | b |\n| --- | --- |\n| 1 | 2 |\n";
+        board.write_markdown(md).unwrap();
+        assert_eq!(backend.change_count(), 1);
+        assert_eq!(
+            board.types().unwrap(),
+            vec![Type::HTML, Type::RTF, Type::String]
+        );
+        assert_eq!(board.get_text(Type::String).unwrap().as_deref(), Some(md));
+
+        let html = board.get_text(Type::HTML).unwrap().unwrap();
+        assert!(html.starts_with(
+            "<meta charset=\"utf-8\"><h1>Notes</h1>\n<ul>\n<li><strong>bold</strong> item</li>"
+        ));
+        let text = AttributedText::from_rtf(&board.get_text(Type::RTF).unwrap().unwrap());
+        let runs: Vec<_> = text.runs().collect();
+        assert!(runs[0].0.starts_with("Notes") && runs[0].1.bold);
+        assert!(runs.iter().any(
+            |(run, style)| run.trim_end() == "code" && style.font.as_deref() == Some("Courier")
+        ));
+        assert!(text.text().contains("a\tb\n1\t2"));
+        assert_eq!(board.get_markdown().unwrap(), md.trim_end());
+
+        board
+            .write_contents(
+                Content::String(r"{\rtf1 {\b Hi} there\par next}".into()),
+                Type::RTF,
+            )
+            .unwrap();
+        assert_eq!(board.get_markdown().unwrap(), "**Hi** there\n\nnext");
+        board
+            .write_contents(Content::String("plain".into()), Type::String)
+            .unwrap();
+        assert!(matches!(
+            board.get_markdown(),
+            Err(ClipboardError::TypeUnavailable(Type::HTML))
+        ));
+    }
+}
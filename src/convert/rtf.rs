@@ -0,0 +1,932 @@
+//! Reading and writing the RTF that Cocoa, Word and friends put on the
+//! pasteboard.
+//!
+//! Text, character formatting, paragraph alignment and hyperlinks are kept;
+//! pictures, style sheets and other destinations are skipped.
+
+use std::fmt::Write;
+
+use encoding_rs::{
+    Encoding, BIG5, EUC_KR, GBK, MACINTOSH, SHIFT_JIS, WINDOWS_1250, WINDOWS_1251, WINDOWS_1252,
+    WINDOWS_1253, WINDOWS_1254, WINDOWS_1255, WINDOWS_1256, WINDOWS_1257, WINDOWS_1258,
+    WINDOWS_874,
+};
+
+use super::{Alignment, AttributedText, Color, RunStyle};
+
+pub fn rtf_to_text(rtf: &str) -> String {
+    read(rtf).text().to_string()
+}
+
+pub fn rtf_to_html(rtf: &str) -> String {
+    read(rtf).to_html()
+}
+
+pub(crate) fn read(rtf: &str) -> AttributedText {
+    let mut parser = Parser::new(rtf.as_bytes());
+    parser.run();
+    parser.out
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Destination {
+    Text,
+    FontTable,
+    ColorTable,
+    /// A field instruction such as `HYPERLINK "url"`.
+    FieldInstruction,
+    Skip,
+}
+
+#[derive(Debug, Clone)]
+struct State {
+    destination: Destination,
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    strikethrough: bool,
+    font: Option<i32>,
+    size: Option<u32>,
+    color: usize,
+    background: usize,
+    alignment: Alignment,
+    link: Option<String>,
+    /// Characters to drop after a `\u` escape.
+    uc: usize,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            destination: Destination::Text,
+            bold: false,
+            italic: false,
+            underline: false,
+            strikethrough: false,
+            font: None,
+            size: None,
+            color: 0,
+            background: 0,
+            alignment: Alignment::Left,
+            link: None,
+            uc: 1,
+        }
+    }
+}
+
+impl State {
+    /// Reset character formatting, as `\plain` does.
+    fn plain(&mut self) {
+        *self = Self {
+            destination: self.destination,
+            alignment: self.alignment,
+            link: self.link.take(),
+            uc: self.uc,
+            ..Self::default()
+        };
+    }
+}
+
+#[derive(Debug, Default)]
+struct Font {
+    name: String,
+    codepage: Option<u32>,
+}
+
+/// Destinations whose content is never text.
+const SKIPPED: &[&str] = &[
+    "author",
+    "buptim",
+    "comment",
+    "creatim",
+    "doccomm",
+    "falt",
+    "footer",
+    "footerf",
+    "footerl",
+    "footerr",
+    "footnote",
+    "header",
+    "headerf",
+    "headerl",
+    "headerr",
+    "info",
+    "keywords",
+    "listoverridetable",
+    "listtable",
+    "object",
+    "operator",
+    "pict",
+    "printim",
+    "private",
+    "revtim",
+    "rsidtbl",
+    "stylesheet",
+    "subject",
+    "title",
+    "xe",
+];
+
+/// Deepest group nesting read; groups nested further are skipped whole.
+const MAX_DEPTH: usize = 256;
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    state: State,
+    stack: Vec<State>,
+    /// Groups open past `MAX_DEPTH`, being skipped.
+    too_deep: usize,
+    fonts: Vec<(i32, Font)>,
+    colors: Vec<Option<Color>>,
+    /// Colour being assembled from `\red`, `\green` and `\blue`.
+    color: Option<Color>,
+    codepage: u32,
+    /// Code-page bytes waiting to be decoded together.
+    pending: Vec<u8>,
+    /// Characters still to skip after a `\u` escape.
+    skip: usize,
+    /// High surrogate waiting for its pair.
+    surrogate: Option<u16>,
+    /// Instruction of the most recent field.
+    field: String,
+    /// Style of `text`, worked out at its first character.
+    style: Option<RunStyle>,
+    /// Text emitted since the style last could have changed.
+    text: String,
+    out: AttributedText,
+}
+
+impl<'a> Parser<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            pos: 0,
+            state: State::default(),
+            stack: Vec::new(),
+            too_deep: 0,
+            fonts: Vec::new(),
+            colors: Vec::new(),
+            color: None,
+            codepage: 1252,
+            pending: Vec::new(),
+            skip: 0,
+            surrogate: None,
+            field: String::new(),
+            style: None,
+            text: String::new(),
+            out: AttributedText::new(),
+        }
+    }
+
+    fn run(&mut self) {
+        while let Some(&byte) = self.bytes.get(self.pos) {
+            self.pos += 1;
+            if self.too_deep > 0 {
+                match byte {
+                    b'{' => self.too_deep += 1,
+                    b'}' => self.too_deep -= 1,
+                    b'\\' => self.pos += 1,
+                    _ => {}
+                }
+                continue;
+            }
+            match byte {
+                b'{' if self.stack.len() >= MAX_DEPTH => {
+                    self.too_deep = 1;
+                }
+                b'{' => {
+                    self.flush_bytes();
+                    self.restyle();
+                    self.skip = 0;
+                    self.stack.push(self.state.clone());
+                    // `{\*\dest ...}` groups we don't understand are skipped.
+                    if self.bytes[self.pos..].starts_with(b"\\*") {
+                        self.pos += 2;
+                        self.state.destination = Destination::Skip;
+                    }
+                }
+                b'}' => {
+                    self.flush_bytes();
+                    self.restyle();
+                    self.skip = 0;
+                    if self.state.destination == Destination::FontTable {
+                        self.finish_font();
+                    }
+                    match self.stack.pop() {
+                        Some(state) => self.state = state,
+                        None => break,
+                    }
+                }
+                b'\\' => self.control(),
+                b'\r' | b'\n' => {}
+                // Writers may leave a trail byte in the ASCII range as is.
+                _ if byte.is_ascii() && self.awaits_trail_byte() => self.pending.push(byte),
+                _ => {
+                    self.flush_bytes();
+                    let start = self.pos - 1;
+                    let len = utf8_len(byte);
+                    let end = (start + len).min(self.bytes.len());
+                    self.pos = end;
+                    let ch = std::str::from_utf8(&self.bytes[start..end])
+                        .ok()
+                        .and_then(|s| s.chars().next())
+                        .unwrap_or('\u{fffd}');
+                    self.char(ch);
+                }
+            }
+        }
+        self.flush_bytes();
+        self.restyle();
+    }
+
+    fn control(&mut self) {
+        let Some(&byte) = self.bytes.get(self.pos) else {
+            return;
+        };
+        if !byte.is_ascii_alphabetic() {
+            self.pos += 1;
+            match byte {
+                b'\'' => {
+                    let hex = self.bytes.get(self.pos..self.pos + 2);
+                    let value = hex
+                        .and_then(|hex| std::str::from_utf8(hex).ok())
+                        .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                    if let Some(value) = value {
+                        self.pos += 2;
+                        if self.skip > 0 {
+                            self.skip -= 1;
+                        } else if self.state.destination == Destination::Text {
+                            self.pending.push(value);
+                        }
+                    }
+                }
+                b'~' => self.char('\u{a0}'),
+                b'_' => self.char('\u{2011}'),
+                b'-' => {}
+                b'\r' | b'\n' => self.char('\n'),
+                b'*' => self.state.destination = Destination::Skip,
+                other if self.awaits_trail_byte() => self.pending.push(other),
+                other => self.char(other as char),
+            }
+            return;
+        }
+
+        let start = self.pos;
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(u8::is_ascii_alphabetic)
+        {
+            self.pos += 1;
+        }
+        let word = std::str::from_utf8(&self.bytes[start..self.pos]).unwrap_or_default();
+        let param_start = self.pos;
+        if self.bytes.get(self.pos) == Some(&b'-') {
+            self.pos += 1;
+        }
+        while self.bytes.get(self.pos).is_some_and(u8::is_ascii_digit) {
+            self.pos += 1;
+        }
+        let param = std::str::from_utf8(&self.bytes[param_start..self.pos])
+            .ok()
+            .and_then(|param| param.parse::<i32>().ok());
+        if self.bytes.get(self.pos) == Some(&b' ') {
+            self.pos += 1;
+        }
+        self.flush_bytes();
+        self.word(word, param);
+    }
+
+    fn word(&mut self, word: &str, param: Option<i32>) {
+        self.restyle();
+        let on = param != Some(0);
+        match word {
+            "fonttbl" => self.state.destination = Destination::FontTable,
+            "colortbl" => {
+                self.state.destination = Destination::ColorTable;
+                self.color = None;
+            }
+            "fldinst" => {
+                self.state.destination = Destination::FieldInstruction;
+                self.field.clear();
+            }
+            "fldrslt" => self.state.link = hyperlink(&self.field),
+            _ if SKIPPED.contains(&word) => self.state.destination = Destination::Skip,
+            "ansicpg" => self.codepage = param.map_or(1252, |cp| cp as u32),
+            // Binary data, such as a picture's, which isn't RTF.
+            "bin" => {
+                let len = param.unwrap_or(0).max(0) as usize;
+                self.pos = self.pos.saturating_add(len).min(self.bytes.len());
+            }
+            "mac" => self.codepage = 10000,
+            "pc" => self.codepage = 437,
+            "uc" => self.state.uc = param.unwrap_or(1).max(0) as usize,
+            "u" => {
+                if let Some(code) = param {
+                    self.unicode(code as i16 as u16);
+                    self.skip = self.state.uc;
+                }
+            }
+            "f" => match self.state.destination {
+                Destination::FontTable => {
+                    self.finish_font();
+                    self.fonts.push((param.unwrap_or(0), Font::default()));
+                }
+                _ => self.state.font = param,
+            },
+            "fcharset" if self.state.destination == Destination::FontTable => {
+                if let Some((_, font)) = self.fonts.last_mut() {
+                    font.codepage = param.and_then(charset_codepage);
+                }
+            }
+            "red" | "green" | "blue" => {
+                let color = self.color.get_or_insert(Color::new(0, 0, 0));
+                let value = param.unwrap_or(0).clamp(0, 255) as u8;
+                match word {
+                    "red" => color.r = value,
+                    "green" => color.g = value,
+                    _ => color.b = value,
+                }
+            }
+            "plain" => self.state.plain(),
+            "b" => self.state.bold = on,
+            "i" => self.state.italic = on,
+            "ul" => self.state.underline = on,
+            "ulnone" => self.state.underline = false,
+            "strike" => self.state.strikethrough = on,
+            "fs" => self.state.size = param.map(|size| size.max(0) as u32),
+            "cf" => self.state.color = param.unwrap_or(0).max(0) as usize,
+            "cb" | "chcbpat" | "highlight" => {
+                self.state.background = param.unwrap_or(0).max(0) as usize
+            }
+            "pard" | "ql" => self.state.alignment = Alignment::Left,
+            "qc" => self.state.alignment = Alignment::Center,
+            "qr" => self.state.alignment = Alignment::Right,
+            "qj" => self.state.alignment = Alignment::Justified,
+            "par" | "line" | "sect" | "page" | "row" => self.char('\n'),
+            "cell" | "tab" => self.char('\t'),
+            "emdash" => self.char('—'),
+            "endash" => self.char('–'),
+            "bullet" => self.char('•'),
+            "lquote" => self.char('‘'),
+            "rquote" => self.char('’'),
+            "ldblquote" => self.char('“'),
+            "rdblquote" => self.char('”'),
+            _ => {}
+        }
+    }
+
+    fn unicode(&mut self, unit: u16) {
+        match unit {
+            0xd800..=0xdbff => self.surrogate = Some(unit),
+            0xdc00..=0xdfff => {
+                if let Some(high) = self.surrogate.take() {
+                    let code =
+                        0x10000 + ((u32::from(high) - 0xd800) << 10) + (u32::from(unit) - 0xdc00);
+                    self.emit(char::from_u32(code).unwrap_or('\u{fffd}'));
+                }
+            }
+            _ => {
+                self.surrogate = None;
+                self.emit(char::from_u32(u32::from(unit)).unwrap_or('\u{fffd}'));
+            }
+        }
+    }
+
+    fn char(&mut self, ch: char) {
+        if self.skip > 0 {
+            self.skip -= 1;
+            return;
+        }
+        match self.state.destination {
+            Destination::Text => self.emit(ch),
+            Destination::FontTable => {
+                if let Some((_, font)) = self.fonts.last_mut() {
+                    font.name.push(ch);
+                }
+            }
+            Destination::ColorTable => {
+                if ch == ';' {
+                    self.colors.push(self.color.take());
+                }
+            }
+            Destination::FieldInstruction => self.field.push(ch),
+            Destination::Skip => {}
+        }
+    }
+
+    fn finish_font(&mut self) {
+        if let Some((_, font)) = self.fonts.last_mut() {
+            let name = font.name.trim().trim_end_matches(';').trim().to_string();
+            font.name = name;
+        }
+    }
+
+    /// The code page of `\'hh` bytes: the current font's, or the document's.
+    fn byte_codepage(&self) -> u32 {
+        self.state
+            .font
+            .and_then(|font| self.font(font))
+            .and_then(|font| font.codepage)
+            .unwrap_or(self.codepage)
+    }
+
+    fn awaits_trail_byte(&self) -> bool {
+        ends_with_lead_byte(&self.pending, self.byte_codepage())
+    }
+
+    fn flush_bytes(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let bytes = std::mem::take(&mut self.pending);
+        for ch in decode_codepage(&bytes, self.byte_codepage()) {
+            self.emit(ch);
+        }
+    }
+
+    fn font(&self, id: i32) -> Option<&Font> {
+        self.fonts
+            .iter()
+            .find(|(font, _)| *font == id)
+            .map(|(_, font)| font)
+    }
+
+    fn emit(&mut self, ch: char) {
+        if self.state.destination != Destination::Text {
+            return;
+        }
+        if self.style.is_none() {
+            self.style = Some(self.run_style());
+        }
+        self.text.push(ch);
+    }
+
+    /// Append the text emitted so far, as the style may be about to change.
+    fn restyle(&mut self) {
+        if let Some(style) = self.style.take() {
+            self.out.push_str(&self.text, &style);
+            self.text.clear();
+        }
+    }
+
+    fn run_style(&self) -> RunStyle {
+        RunStyle {
+            bold: self.state.bold,
+            italic: self.state.italic,
+            underline: self.state.underline,
+            strikethrough: self.state.strikethrough,
+            font: self
+                .state
+                .font
+                .and_then(|font| self.font(font))
+                .map(|font| font.name.clone())
+                .filter(|name| !name.is_empty()),
+            size: self.state.size,
+            color: self.colors.get(self.state.color).copied().flatten(),
+            background: self.colors.get(self.state.background).copied().flatten(),
+            link: self.state.link.clone(),
+            alignment: self.state.alignment,
+        }
+    }
+}
+
+/// The target of a `HYPERLINK "url"` field instruction.
+fn hyperlink(instruction: &str) -> Option<String> {
+    let rest = instruction.trim_start();
+    if !rest.get(..9)?.eq_ignore_ascii_case("HYPERLINK") {
+        return None;
+    }
+    let rest = rest[9..].trim_start();
+    let url = match rest.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next()?,
+        None => rest.split_whitespace().next()?,
+    };
+    Some(url.to_string()).filter(|url| !url.is_empty())
+}
+
+fn utf8_len(first: u8) -> usize {
+    match first {
+        0xf0..=0xff => 4,
+        0xe0..=0xef => 3,
+        0xc0..=0xdf => 2,
+        _ => 1,
+    }
+}
+
+/// Windows code page for an RTF `\fcharset`.
+fn charset_codepage(charset: i32) -> Option<u32> {
+    let codepage = match charset {
+        0 => 1252,
+        77 => 10000,
+        128 => 932,
+        129 => 949,
+        134 => 936,
+        136 => 950,
+        161 => 1253,
+        162 => 1254,
+        163 => 1258,
+        177 => 1255,
+        178 => 1256,
+        186 => 1257,
+        204 => 1251,
+        222 => 874,
+        238 => 1250,
+        _ => return None,
+    };
+    Some(codepage)
+}
+
+/// The encoding of a Windows code page, or `None` for those we can't
+/// decode.
+fn codepage_encoding(codepage: u32) -> Option<&'static Encoding> {
+    let encoding = match codepage {
+        874 => WINDOWS_874,
+        932 => SHIFT_JIS,
+        936 => GBK,
+        949 => EUC_KR,
+        950 => BIG5,
+        1250 => WINDOWS_1250,
+        1251 => WINDOWS_1251,
+        1252 => WINDOWS_1252,
+        1253 => WINDOWS_1253,
+        1254 => WINDOWS_1254,
+        1255 => WINDOWS_1255,
+        1256 => WINDOWS_1256,
+        1257 => WINDOWS_1257,
+        1258 => WINDOWS_1258,
+        10000 => MACINTOSH,
+        _ => return None,
+    };
+    Some(encoding)
+}
+
+/// Whether `bytes` end with the lead byte of a double-byte character,
+/// still waiting for its trail byte.
+fn ends_with_lead_byte(bytes: &[u8], codepage: u32) -> bool {
+    let is_lead = |byte: u8| match codepage {
+        932 => matches!(byte, 0x81..=0x9f | 0xe0..=0xfc),
+        936 | 949 | 950 => matches!(byte, 0x81..=0xfe),
+        _ => false,
+    };
+    let mut idx = 0;
+    while idx < bytes.len() {
+        idx += if is_lead(bytes[idx]) { 2 } else { 1 };
+    }
+    idx > bytes.len()
+}
+
+fn decode_codepage(bytes: &[u8], codepage: u32) -> Vec<char> {
+    match codepage_encoding(codepage) {
+        Some(encoding) => encoding
+            .decode_without_bom_handling(bytes)
+            .0
+            .chars()
+            .collect(),
+        None => bytes
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii() {
+                    byte as char
+                } else {
+                    '\u{fffd}'
+                }
+            })
+            .collect(),
+    }
+}
+
+pub(crate) fn write(text: &AttributedText) -> String {
+    let mut fonts: Vec<&str> = Vec::new();
+    let mut colors: Vec<Color> = Vec::new();
+    for (_, style) in text.runs() {
+        if let Some(font) = style.font.as_deref() {
+            if !fonts.contains(&font) {
+                fonts.push(font);
+            }
+        }
+        for color in [style.color, style.background].into_iter().flatten() {
+            if !colors.contains(&color) {
+                colors.push(color);
+            }
+        }
+    }
+
+    let mut out = String::from("{\\rtf1\\ansi\\ansicpg1252\\uc1");
+    if !fonts.is_empty() {
+        out.push_str("{\\fonttbl");
+        for (idx, font) in fonts.iter().enumerate() {
+            let _ = write!(out, "{{\\f{idx}\\fnil ");
+            escape(&mut out, font);
+            out.push_str(";}");
+        }
+        out.push('}');
+    }
+    if !colors.is_empty() {
+        out.push_str("{\\colortbl;");
+        for Color { r, g, b } in &colors {
+            let _ = write!(out, "\\red{r}\\green{g}\\blue{b};");
+        }
+        out.push('}');
+    }
+    out.push('\n');
+
+    let color_index = |color: Color| colors.iter().position(|c| *c == color).unwrap_or(0) + 1;
+    let mut alignment = Alignment::Left;
+    let mut paragraph_start = true;
+    // Each styled run is its own group, so nothing needs resetting after it.
+    for (run, style) in text.runs() {
+        let mut controls = String::new();
+        for (on, word) in [
+            (style.bold, "\\b"),
+            (style.italic, "\\i"),
+            (style.underline, "\\ul"),
+            (style.strikethrough, "\\strike"),
+        ] {
+            if on {
+                controls.push_str(word);
+            }
+        }
+        if let Some(font) = style.font.as_deref() {
+            let idx = fonts.iter().position(|f| *f == font).unwrap_or(0);
+            let _ = write!(controls, "\\f{idx}");
+        }
+        if let Some(size) = style.size {
+            let _ = write!(controls, "\\fs{size}");
+        }
+        if let Some(color) = style.color {
+            let _ = write!(controls, "\\cf{}", color_index(color));
+        }
+        if let Some(background) = style.background {
+            let _ = write!(controls, "\\cb{}", color_index(background));
+        }
+
+        // Alignment can only change where a run starts a paragraph.
+        if paragraph_start && style.alignment != alignment {
+            alignment = style.alignment;
+            out.push_str(match alignment {
+                Alignment::Left => "\\pard ",
+                Alignment::Center => "\\pard\\qc ",
+                Alignment::Right => "\\pard\\qr ",
+                Alignment::Justified => "\\pard\\qj ",
+            });
+        }
+        paragraph_start = run.ends_with('\n');
+        if let Some(link) = &style.link {
+            out.push_str("{\\field{\\*\\fldinst{HYPERLINK \"");
+            escape(&mut out, &link.replace('"', "%22"));
+            out.push_str("\"}}{\\fldrslt ");
+        }
+        if controls.is_empty() {
+            escape(&mut out, run);
+        } else {
+            out.push('{');
+            out.push_str(&controls);
+            out.push(' ');
+            escape(&mut out, run);
+            out.push('}');
+        }
+        if style.link.is_some() {
+            out.push_str("}}");
+        }
+    }
+    out.push('}');
+    out
+}
+
+fn escape(out: &mut String, text: &str) {
+    for ch in text.chars() {
+        match ch {
+            '\\' | '{' | '}' => {
+                out.push('\\');
+                out.push(ch);
+            }
+            '\n' => out.push_str("\\par\n"),
+            '\t' => out.push_str("\\tab "),
+            '\u{a0}' => out.push_str("\\~"),
+            ' '..='~' => out.push(ch),
+            _ if ch.is_control() => {}
+            _ => {
+                let mut units = [0; 2];
+                for unit in ch.encode_utf16(&mut units) {
+                    let _ = write!(out, "\\u{}?", *unit as i16);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COCOA: &str = r"{\rtf1\ansi\ansicpg1252\cocoartf2709
+\cocoatextscaling0\cocoaplatform0{\fonttbl\f0\fswiss\fcharset0 Helvetica;\f1\fnil\fcharset0 Menlo-Regular;}
+{\colortbl;\red255\green255\blue255;\red251\green0\blue7;}
+{\*\expandedcolortbl;;\cssrgb\c100000\c0\c0;}
+\paperw11900\paperh16840\margl1440\margr1440\vieww11520\viewh8400\viewkind0
+\pard\tx566\tx1133\pardirnatural\partightenfactor0
+
+\f0\fs24 \cf0 Hello \b bold\b0  and \i italic\i0 .\
+\cf2 Red \ul under\ulnone \
+\f1\fs20 \cf0 code\uc0\u8217 s}";
+
+    #[test]
+    fn cocoa_text() {
+        assert_eq!(
+            rtf_to_text(COCOA),
+            "Hello bold and italic.\nRed under\ncode’s"
+        );
+    }
+
+    #[test]
+    fn cocoa_runs() {
+        let doc = read(COCOA);
+        let base = RunStyle {
+            font: Some("Helvetica".into()),
+            size: Some(24),
+            ..RunStyle::default()
+        };
+        let red = Some(Color::new(251, 0, 7));
+        let runs: Vec<_> = doc
+            .runs()
+            .map(|(text, style)| (text, style.clone()))
+            .collect();
+        assert_eq!(
+            runs,
+            vec![
+                ("Hello ", base.clone()),
+                (
+                    "bold",
+                    RunStyle {
+                        bold: true,
+                        ..base.clone()
+                    }
+                ),
+                (" and ", base.clone()),
+                (
+                    "italic",
+                    RunStyle {
+                        italic: true,
+                        ..base.clone()
+                    }
+                ),
+                (".\n", base.clone()),
+                (
+                    "Red ",
+                    RunStyle {
+                        color: red,
+                        ..base.clone()
+                    }
+                ),
+                (
+                    "under",
+                    RunStyle {
+                        color: red,
+                        underline: true,
+                        ..base.clone()
+                    }
+                ),
+                (
+                    "\n",
+                    RunStyle {
+                        color: red,
+                        ..base.clone()
+                    }
+                ),
+                (
+                    "code’s",
+                    RunStyle {
+                        font: Some("Menlo-Regular".into()),
+                        size: Some(20),
+                        ..RunStyle::default()
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            rtf_to_text(r"{\rtf1\ansi caf\'e9 \'93q\'94 \{x\} a\\b\~c\tab d\par}"),
+            "café “q” {x} a\\b\u{a0}c\td\n"
+        );
+        assert_eq!(rtf_to_text(r"{\rtf1\mac caf\'8e}"), "café");
+        // `\uN` with its fallback characters, negative values and a
+        // surrogate pair.
+        assert_eq!(
+            rtf_to_text(r"{\rtf1\u-3913?\uc2\u20013\'3f\'3f!\uc1\u-10179?\u-8704?}"),
+            "\u{f0b7}中!😀"
+        );
+    }
+
+    #[test]
+    fn code_pages() {
+        assert_eq!(
+            rtf_to_text(r"{\rtf1\ansi\ansicpg1251 \'cf\'f0\'e8\'e2\'e5\'f2}"),
+            "Привет"
+        );
+        // Font charsets win over the document code page, and double-byte
+        // characters may leave their trail byte unescaped.
+        let rtf = r"{\rtf1\ansi\ansicpg1252{\fonttbl{\f0\fcharset128 MS Gothic;}{\f1\fcharset134 SimSun;}{\f2\fcharset238 Arial;}}
+\f0 \'83e\'83X\'83g\'81\\ \f1 \'d6\'d0\'ce\'c4 \f2 \'9ea\'9d}";
+        assert_eq!(rtf_to_text(rtf), "テスト― 中文 žať");
+        assert_eq!(rtf_to_text(r"{\rtf1\ansicpg437 a\'80}"), "a\u{fffd}");
+    }
+
+    #[test]
+    fn groups_and_destinations() {
+        let rtf = r#"{\rtf1{\info{\title Secret}{\author Me}}{\*\generator Word;}
+{\b bold {\i both} bold}\plain plain{\field{\*\fldinst HYPERLINK "x"}{\fldrslt link}}
+{\pict\pngblip 89504e47}}"#;
+        let doc = read(rtf);
+        assert_eq!(doc.text(), "bold both boldplainlink");
+        let runs: Vec<_> = doc.runs().collect();
+        assert!(runs[0].1.bold && !runs[0].1.italic);
+        assert!(runs[1].1.bold && runs[1].1.italic);
+    }
+
+    #[test]
+    fn hostile() {
+        let deep = format!(
+            r"{{\rtf1 a{}b{}c}}",
+            "{".repeat(100_000),
+            "}".repeat(100_000)
+        );
+        assert_eq!(rtf_to_text(&deep), "ac");
+        // `\bin` data is skipped by its length, whatever it holds.
+        let doc = read(r"{\rtf1 a\bin5 {\b }b{}c\bin}");
+        assert_eq!(doc.text(), "abc");
+        assert_eq!(doc.runs().count(), 1);
+        let doc = read(&format!(r"{{\rtf1 {} \b b\b0 c}}", "a".repeat(100_000)));
+        assert_eq!(doc.runs().count(), 3);
+    }
+
+    #[test]
+    fn malformed() {
+        for rtf in [
+            "",
+            "{",
+            "}}}",
+            r"{\rtf1 \'",
+            r"{\rtf1 \'zz",
+            r"{\rtf1 \u",
+            r"{\rtf1\fonttbl",
+            "{\\rtf1 \\",
+            r"{\colortbl\red999;}",
+        ] {
+            read(rtf);
+        }
+        assert_eq!(rtf_to_text(r"{\rtf1 a}}b"), "a");
+    }
+
+    #[test]
+    fn write_round_trip() {
+        let doc = read(COCOA);
+        let rtf = write(&doc);
+        assert!(rtf.starts_with(r"{\rtf1\ansi"));
+        assert_eq!(read(&rtf), doc);
+
+        let mut text = AttributedText::from("{a\\b}\tc\u{a0}d ");
+        text.push_str(
+            "caf\u{e9} \u{1f600}\n",
+            &RunStyle {
+                strikethrough: true,
+                ..RunStyle::default()
+            },
+        );
+        let rtf = write(&text);
+        assert!(rtf.contains(r"\{a\\b\}\tab c\~d {\strike caf\u233? \u-10179?\u-8704?\par"));
+        assert_eq!(read(&rtf), text);
+    }
+
+    #[test]
+    fn fields_and_paragraphs() {
+        let rtf = r#"{\rtf1{\colortbl;\red255\green255\blue0;}
+\pard\qc Centred\par
+\pard\qr {\highlight1 marked}\par
+\pard Go to {\field{\*\fldinst{HYPERLINK "https://example.com" \\o "tip"}}{\fldrslt{\ul here}}}.}"#;
+        let doc = read(rtf);
+        assert_eq!(doc.text(), "Centred\nmarked\nGo to here.");
+        let runs: Vec<_> = doc.runs().collect();
+        assert_eq!(
+            runs[0],
+            (
+                "Centred\n",
+                &RunStyle {
+                    alignment: Alignment::Center,
+                    ..RunStyle::default()
+                }
+            )
+        );
+        assert_eq!(runs[1].1.background, Some(Color::new(255, 255, 0)));
+        assert_eq!(runs[1].1.alignment, Alignment::Right);
+        assert_eq!(runs[4].0, "here");
+        assert_eq!(runs[4].1.link.as_deref(), Some("https://example.com"));
+        assert!(runs[4].1.underline);
+        assert_eq!(runs[5], (".", &RunStyle::default()));
+        assert_eq!(read(&write(&doc)), doc);
+    }
+}
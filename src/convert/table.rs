@@ -0,0 +1,792 @@
+use super::css::declarations;
+use super::html::{escape, tokenize, Token, VOID};
+use super::markdown::escape_inline;
+use super::text::{BLOCK, HIDDEN, PARAGRAPH};
+use crate::{ClipboardBackend, ClipboardError, Content, PasteBoard, Type};
+
+/// Rows of text cells, as copied from a spreadsheet. Rows may have
+/// different lengths; conversions pad them to the widest.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Table {
+    rows: Vec<Vec<String>>,
+    header: bool,
+}
+
+impl Table {
+    pub fn new(rows: Vec<Vec<String>>) -> Self {
+        Self {
+            rows,
+            header: false,
+        }
+    }
+
+    /// Whether the first row holds column titles.
+    pub fn with_header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Parse tab-separated values the way spreadsheet apps write them: a
+    /// cell starting with `"` is quoted, may hold tabs and newlines, and
+    /// writes `"` as `""`. The header is detected with `detect_header`.
+    pub fn from_tsv(tsv: &str) -> Self {
+        let mut rows = Vec::new();
+        let mut row = Vec::new();
+        let mut cell = String::new();
+        let mut chars = tsv.chars().peekable();
+        let mut at_cell_start = true;
+        while let Some(ch) = chars.next() {
+            match ch {
+                '"' if at_cell_start => {
+                    while let Some(ch) = chars.next() {
+                        match ch {
+                            '"' if chars.peek() == Some(&'"') => {
+                                cell.push('"');
+                                chars.next();
+                            }
+                            '"' => break,
+                            _ => cell.push(ch),
+                        }
+                    }
+                    at_cell_start = false;
+                    continue;
+                }
+                '\t' => row.push(std::mem::take(&mut cell)),
+                '\r' if chars.peek() == Some(&'\n') => continue,
+                '\n' | '\r' => {
+                    row.push(std::mem::take(&mut cell));
+                    rows.push(std::mem::take(&mut row));
+                }
+                _ => cell.push(ch),
+            }
+            at_cell_start = matches!(ch, '\t' | '\n' | '\r');
+        }
+        if !at_cell_start || !row.is_empty() {
+            row.push(cell);
+            rows.push(row);
+        }
+        let mut table = Self::new(rows);
+        table.header = table.detect_header();
+        table
+    }
+
+    /// Guess whether the first row is a header: its cells are distinct,
+    /// non-empty and not numbers, and some column below it is numeric.
+    pub fn detect_header(&self) -> bool {
+        let Some(first) = self.rows.first() else {
+            return false;
+        };
+        self.rows.len() > 1
+            && first.iter().enumerate().all(|(idx, cell)| {
+                !cell.trim().is_empty() && !is_number(cell) && !first[..idx].contains(cell)
+            })
+            && (0..self.columns()).any(|column| is_numeric(&self.rows[1..], column))
+    }
+
+    pub fn rows(&self) -> &[Vec<String>] {
+        &self.rows
+    }
+
+    pub fn has_header(&self) -> bool {
+        self.header
+    }
+
+    /// The header row, if the table has one.
+    pub fn header(&self) -> Option<&[String]> {
+        self.rows.first().filter(|_| self.header).map(Vec::as_slice)
+    }
+
+    /// The rows below the header.
+    pub fn body(&self) -> &[Vec<String>] {
+        match self.header() {
+            Some(_) => &self.rows[1..],
+            None => &self.rows,
+        }
+    }
+
+    /// The number of columns in the widest row.
+    pub fn columns(&self) -> usize {
+        self.rows.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    fn numeric(&self, column: usize) -> bool {
+        is_numeric(self.body(), column)
+    }
+
+    /// Rows padded to the same number of cells.
+    fn padded(&self) -> impl Iterator<Item = Vec<&str>> {
+        let columns = self.columns();
+        self.rows.iter().map(move |row| {
+            let mut cells: Vec<&str> = row.iter().map(String::as_str).collect();
+            cells.resize(columns, "");
+            cells
+        })
+    }
+
+    /// Tab-separated values, quoting cells the way `from_tsv` reads them.
+    pub fn to_tsv(&self) -> String {
+        self.join('\t', |cell| {
+            cell.contains(['\t', '\n', '\r']) || cell.starts_with('"')
+        })
+    }
+
+    /// Comma-separated values as in RFC 4180, with `\n` line endings.
+    pub fn to_csv(&self) -> String {
+        self.join(',', |cell| cell.contains([',', '"', '\n', '\r']))
+    }
+
+    fn join(&self, separator: char, needs_quotes: impl Fn(&str) -> bool) -> String {
+        let mut out = String::new();
+        for row in &self.rows {
+            for (idx, cell) in row.iter().enumerate() {
+                if idx > 0 {
+                    out.push(separator);
+                }
+                if needs_quotes(cell) {
+                    out.push('"');
+                    out.push_str(&cell.replace('"', "\"\""));
+                    out.push('"');
+                } else {
+                    out.push_str(cell);
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut out = String::from("<table>\n");
+        let row = |out: &mut String, cells: Vec<&str>, tag: &str| {
+            out.push_str("<tr>");
+            for cell in cells {
+                let cell = escape(cell).replace('\n', "<br>");
+                out.push_str(&format!("<{tag}>{cell}</{tag}>"));
+            }
+            out.push_str("</tr>\n");
+        };
+        let mut rows = self.padded();
+        if self.header {
+            if let Some(cells) = rows.next() {
+                out.push_str("<thead>\n");
+                row(&mut out, cells, "th");
+                out.push_str("</thead>\n");
+            }
+        }
+        out.push_str("<tbody>\n");
+        for cells in rows {
+            row(&mut out, cells, "td");
+        }
+        out.push_str("</tbody>\n</table>");
+        out
+    }
+
+    /// A GFM table. Numeric columns are right-aligned, and a table without
+    /// a header gets an empty one, which Markdown requires.
+    pub fn to_markdown(&self) -> String {
+        let columns = self.columns();
+        if columns == 0 {
+            return String::new();
+        }
+        let line = |cells: Vec<String>| format!("| {} |", cells.join(" | "));
+        let cell = |cell: &str| {
+            escape_inline(cell)
+                .replace('|', "\\|")
+                .replace(['\r', '\n'], " ")
+        };
+        let mut rows = self.padded();
+        let header = if self.header {
+            rows.next().unwrap_or_default()
+        } else {
+            vec![""; columns]
+        };
+        let mut lines = vec![line(header.into_iter().map(cell).collect())];
+        lines.push(line(
+            (0..columns)
+                .map(|column| if self.numeric(column) { "---:" } else { "---" }.to_string())
+                .collect(),
+        ));
+        lines.extend(rows.map(|cells| line(cells.into_iter().map(cell).collect())));
+        lines.join("\n")
+    }
+
+    /// The table drawn with ASCII borders in a monospaced layout, numeric
+    /// columns right-aligned.
+    pub fn to_ascii(&self) -> String {
+        let rows: Vec<Vec<String>> = self
+            .padded()
+            .map(|cells| {
+                cells
+                    .into_iter()
+                    .map(|cell| cell.replace(['\r', '\n'], " "))
+                    .collect()
+            })
+            .collect();
+        let widths: Vec<usize> = (0..self.columns())
+            .map(|column| {
+                rows.iter()
+                    .map(|row| row[column].chars().count())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let rule = widths
+            .iter()
+            .map(|width| "-".repeat(width + 2))
+            .collect::<Vec<_>>()
+            .join("+");
+        let rule = format!("+{rule}+\n");
+
+        let mut out = rule.clone();
+        for (idx, row) in rows.iter().enumerate() {
+            out.push('|');
+            for (column, cell) in row.iter().enumerate() {
+                let pad = " ".repeat(widths[column] - cell.chars().count());
+                let right = self.numeric(column) && !(self.header && idx == 0);
+                if right {
+                    out.push_str(&format!(" {pad}{cell} |"));
+                } else {
+                    out.push_str(&format!(" {cell}{pad} |"));
+                }
+            }
+            out.push('\n');
+            if self.header && idx == 0 {
+                out.push_str(&rule);
+            }
+        }
+        out.push_str(&rule);
+        out
+    }
+}
+
+/// Whether `cell` reads as a number, allowing a sign, thousands
+/// separators, a currency symbol or a percent sign.
+fn is_number(cell: &str) -> bool {
+    let cell = cell.trim();
+    let cell = cell.strip_suffix('%').unwrap_or(cell);
+    let cell = cell.trim_start_matches(['-', '+']);
+    let cell = cell.trim_start_matches(['$', '€', '£', '¥']);
+    let digits: String = cell.chars().filter(|ch| *ch != ',').collect();
+    digits.starts_with(|ch: char| ch.is_ascii_digit() || ch == '.')
+        && digits.parse::<f64>().is_ok_and(f64::is_finite)
+}
+
+/// Whether every non-empty cell of `column` is a number, and there is at
+/// least one.
+fn is_numeric(rows: &[Vec<String>], column: usize) -> bool {
+    let mut cells = rows
+        .iter()
+        .filter_map(|row| row.get(column))
+        .filter(|cell| !cell.trim().is_empty())
+        .peekable();
+    cells.peek().is_some() && cells.all(|cell| is_number(cell))
+}
+
+/// Extract every top-level `<table>` in `html`, as spreadsheet apps publish
+/// them. Merged cells keep their text in the top-left cell and leave the
+/// rest of the span empty, as in their tab-separated form. Styling is
+/// dropped, as is text hidden with `display: none` or `mso-hide: all`;
+/// tables nested in a cell are flattened into its text.
+pub fn tables_from_html(html: &str) -> Vec<Table> {
+    let mut extractor = Extractor::default();
+    for token in tokenize(html) {
+        match &token {
+            Token::Start {
+                name, self_closing, ..
+            } => {
+                extractor.start(name, &token);
+                if *self_closing {
+                    extractor.end(name);
+                }
+            }
+            Token::End { name } => extractor.end(name),
+            Token::Text(text) => extractor.text(text),
+        }
+    }
+    extractor.finish_table();
+    extractor.tables
+}
+
+/// Largest `colspan` or `rowspan` honoured.
+const MAX_SPAN: usize = 1000;
+/// Most cells the grid of one table may hold, counting those spans fill
+/// and the padding to a rectangle; the table is cut off where it would
+/// grow past this.
+const MAX_CELLS: usize = 1 << 16;
+
+#[derive(Debug)]
+struct Cell {
+    text: String,
+    colspan: usize,
+    rowspan: usize,
+    /// Owed collapsed whitespace.
+    space: bool,
+}
+
+#[derive(Debug, Default)]
+struct TableState {
+    /// Cells by row and column; `None` where nothing has been placed yet.
+    grid: Vec<Vec<Option<String>>>,
+    /// Rows started so far.
+    rows: usize,
+    /// Length of the longest row in `grid`.
+    width: usize,
+    /// Whether the table reached `MAX_CELLS` and takes nothing more.
+    full: bool,
+    in_row: bool,
+    in_thead: bool,
+    /// Whether the current row holds only `<th>` cells.
+    all_th: bool,
+    /// Whether the first row is in `<thead>` or made of `<th>` cells.
+    header: bool,
+    cell: Option<Cell>,
+}
+
+impl TableState {
+    fn start_row(&mut self) {
+        self.end_row();
+        let height = self.grid.len().max(self.rows + 1);
+        self.full |= height * self.width.max(1) > MAX_CELLS;
+        if self.full {
+            return;
+        }
+        self.in_row = true;
+        self.all_th = true;
+        self.rows += 1;
+        if self.grid.len() < self.rows {
+            self.grid.resize(self.rows, Vec::new());
+        }
+    }
+
+    fn end_row(&mut self) {
+        if !self.in_row {
+            return;
+        }
+        self.end_cell();
+        if self.rows == 1 {
+            self.header = self.in_thead || (self.all_th && !self.grid[0].is_empty());
+        }
+        self.in_row = false;
+    }
+
+    fn start_cell(&mut self, token: &Token, header: bool) {
+        if !self.in_row {
+            self.start_row();
+        }
+        self.end_cell();
+        if self.full {
+            return;
+        }
+        self.all_th &= header;
+        let span = |attr: &str| {
+            token
+                .attr(attr)
+                .and_then(|span| span.trim().parse::<usize>().ok())
+                .unwrap_or(1)
+                .clamp(1, MAX_SPAN)
+        };
+        self.cell = Some(Cell {
+            text: String::new(),
+            colspan: span("colspan"),
+            rowspan: span("rowspan"),
+            space: false,
+        });
+    }
+
+    /// Place the open cell in the first free column of the current row,
+    /// filling the rest of its span with empty cells.
+    fn end_cell(&mut self) {
+        let Some(cell) = self.cell.take() else {
+            return;
+        };
+        let row = self.rows - 1;
+        let column = self.grid[row]
+            .iter()
+            .position(Option::is_none)
+            .unwrap_or(self.grid[row].len());
+        let width = self.width.max(column + cell.colspan);
+        self.full |= self.grid.len().max(row + cell.rowspan) * width > MAX_CELLS;
+        if self.full {
+            return;
+        }
+        self.width = width;
+        if self.grid.len() < row + cell.rowspan {
+            self.grid.resize(row + cell.rowspan, Vec::new());
+        }
+        let text = cell.text.trim().to_string();
+        for (r, cells) in self.grid[row..row + cell.rowspan].iter_mut().enumerate() {
+            if cells.len() < column + cell.colspan {
+                cells.resize(column + cell.colspan, None);
+            }
+            for (c, slot) in cells[column..column + cell.colspan].iter_mut().enumerate() {
+                *slot = Some(if r == 0 && c == 0 {
+                    text.clone()
+                } else {
+                    String::new()
+                });
+            }
+        }
+    }
+
+    fn finish(mut self) -> Option<Table> {
+        self.end_row();
+        // Row spans reaching past the last row add no rows.
+        self.grid.truncate(self.rows);
+        if self.grid.iter().all(Vec::is_empty) {
+            return None;
+        }
+        let rows = self
+            .grid
+            .into_iter()
+            .map(|row| row.into_iter().map(Option::unwrap_or_default).collect())
+            .collect();
+        let table = Table::new(rows);
+        let header = self.header || table.detect_header();
+        Some(table.with_header(header))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Extractor {
+    tables: Vec<Table>,
+    table: TableState,
+    /// Depth of nested `<table>` elements; only the outermost is extracted.
+    depth: usize,
+    /// Elements open inside hidden content, innermost last.
+    hidden: Vec<String>,
+}
+
+impl Extractor {
+    fn start(&mut self, name: &str, token: &Token) {
+        let void = VOID.contains(&name);
+        if !self.hidden.is_empty() || HIDDEN.contains(&name) || is_hidden(token) {
+            if !void {
+                self.hidden.push(name.to_string());
+            }
+            return;
+        }
+        match name {
+            "table" => {
+                self.depth += 1;
+                if self.depth == 1 {
+                    self.table = TableState::default();
+                } else {
+                    self.line_break();
+                }
+            }
+            _ if self.depth != 1 => {
+                if self.depth > 1 && (BLOCK.contains(&name) || name == "br") {
+                    self.line_break();
+                } else if matches!(name, "td" | "th") {
+                    self.space();
+                }
+            }
+            "tr" => self.table.start_row(),
+            "td" | "th" => self.table.start_cell(token, name == "th"),
+            "thead" => self.table.in_thead = true,
+            "tbody" | "tfoot" => {
+                self.table.end_row();
+                self.table.in_thead = false;
+            }
+            "br" => {
+                if let Some(cell) = &mut self.table.cell {
+                    if !cell.text.is_empty() {
+                        cell.text.push('\n');
+                    }
+                    cell.space = false;
+                }
+            }
+            _ if BLOCK.contains(&name) || PARAGRAPH.contains(&name) => self.line_break(),
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, name: &str) {
+        if !self.hidden.is_empty() {
+            // Like a browser, also close whatever was left open inside,
+            // such as a `<p>` without its end tag.
+            if let Some(idx) = self.hidden.iter().rposition(|open| open == name) {
+                self.hidden.truncate(idx);
+            }
+            return;
+        }
+        match name {
+            "table" if self.depth == 1 => self.finish_table(),
+            "table" => {
+                self.depth = self.depth.saturating_sub(1);
+                self.line_break();
+            }
+            _ if self.depth > 1 && BLOCK.contains(&name) => self.line_break(),
+            _ if self.depth != 1 => {}
+            "tr" => self.table.end_row(),
+            "td" | "th" => self.table.end_cell(),
+            "thead" => {
+                self.table.end_row();
+                self.table.in_thead = false;
+            }
+            _ if BLOCK.contains(&name) || PARAGRAPH.contains(&name) => self.line_break(),
+            _ => {}
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if !self.hidden.is_empty() {
+            return;
+        }
+        let Some(cell) = &mut self.table.cell else {
+            return;
+        };
+        let text = text.replace('\u{a0}', " ");
+        if text.starts_with(|c: char| c.is_ascii_whitespace()) {
+            cell.space = true;
+        }
+        for word in text.split_ascii_whitespace() {
+            if cell.space && !cell.text.is_empty() && !cell.text.ends_with('\n') {
+                cell.text.push(' ');
+            }
+            cell.text.push_str(word);
+            cell.space = true;
+        }
+        cell.space = text.ends_with(|c: char| c.is_ascii_whitespace());
+    }
+
+    fn space(&mut self) {
+        if let Some(cell) = &mut self.table.cell {
+            cell.space = true;
+        }
+    }
+
+    /// Start a new line in the open cell, unless it is empty or already on
+    /// a new line.
+    fn line_break(&mut self) {
+        if let Some(cell) = &mut self.table.cell {
+            if !cell.text.is_empty() && !cell.text.ends_with('\n') {
+                cell.text.push('\n');
+            }
+            cell.space = false;
+        }
+    }
+
+    fn finish_table(&mut self) {
+        if self.depth > 0 {
+            self.tables.extend(std::mem::take(&mut self.table).finish());
+        }
+        self.depth = 0;
+    }
+}
+
+/// Whether `token` is styled as hidden, as Word does with `mso-hide: all`.
+fn is_hidden(token: &Token) -> bool {
+    token.attr("style").is_some_and(|style| {
+        declarations(style).any(|(property, value)| {
+            let value = value.to_ascii_lowercase();
+            (property == "display" && value == "none") || (property == "mso-hide" && value == "all")
+        })
+    })
+}
+
+impl<B: ClipboardBackend> PasteBoard<B> {
+    /// The clipboard's `Type::TabularText` as a table, or else the first
+    /// table in its `Type::HTML`.
+    pub fn get_table(&self) -> Result<Table, ClipboardError> {
+        if let Some(tsv) = self.get_text(Type::TabularText)? {
+            return Ok(Table::from_tsv(&tsv));
+        }
+        self.get_text(Type::HTML)?
+            .and_then(|html| tables_from_html(&html).into_iter().next())
+            .ok_or(ClipboardError::TypeUnavailable(Type::TabularText))
+    }
+
+    /// Publish `table` as `Type::TabularText` for spreadsheets, `Type::HTML`
+    /// for rich text editors and an aligned `Type::String`, in one
+    /// transaction.
+    pub fn write_table(&self, table: &Table) -> Result<(), ClipboardError> {
+        self.writer()
+            .add(Type::TabularText, Content::String(table.to_tsv().into()))
+            .add(Type::HTML, Content::String(table.to_html().into()))
+            .add(Type::String, Content::String(table.to_ascii().into()))
+            .commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryPasteBoard;
+
+    fn table(rows: &[&[&str]]) -> Table {
+        Table::new(
+            rows.iter()
+                .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn tsv() {
+        let tsv = "Name\tNote\tQty\r\npear\t\"two\nlines\"\t1,200\n\"say \"\"hi\"\"\"\ta\"b\t-3.5\n\ttrailing\t\n";
+        let parsed = Table::from_tsv(tsv);
+        assert_eq!(
+            parsed,
+            table(&[
+                &["Name", "Note", "Qty"],
+                &["pear", "two\nlines", "1,200"],
+                &["say \"hi\"", "a\"b", "-3.5"],
+                &["", "trailing", ""],
+            ])
+            .with_header(true)
+        );
+        assert_eq!(Table::from_tsv(&parsed.to_tsv()), parsed);
+        assert_eq!(Table::from_tsv(""), Table::default());
+        assert_eq!(Table::from_tsv("a\tb"), table(&[&["a", "b"]]));
+
+        // No numeric column: nothing marks the first row as a header.
+        assert!(!Table::from_tsv("a\tb\nc\td\n").has_header());
+        assert!(!Table::from_tsv("1\t2\n3\t4\n").has_header());
+    }
+
+    #[test]
+    fn conversions() {
+        let table = table(&[
+            &["Item", "Price"],
+            &["tea, green", "$4"],
+            &["a|b*", "12.50"],
+            &["x"],
+        ])
+        .with_header(true);
+        assert_eq!(table.body().len(), 3);
+        assert_eq!(
+            table.to_csv(),
+            "Item,Price\n\"tea, green\",$4\na|b*,12.50\nx\n"
+        );
+        assert_eq!(
+            table.to_html(),
+            "<table>\n<thead>\n<tr><th>Item</th><th>Price</th></tr>\n</thead>\n<tbody>\n\
+             <tr><td>tea, green</td><td>$4</td></tr>\n<tr><td>a|b*</td><td>12.50</td></tr>\n\
+             <tr><td>x</td><td></td></tr>\n</tbody>\n</table>"
+        );
+        assert_eq!(
+            table.to_markdown(),
+            "| Item | Price |\n| --- | ---: |\n| tea, green | $4 |\n| a\\|b\\* | 12.50 |\n| x |  |"
+        );
+        assert_eq!(
+            table.to_ascii(),
+            "+------------+-------+\n\
+             | Item       | Price |\n\
+             +------------+-------+\n\
+             | tea, green |    $4 |\n\
+             | a|b*       | 12.50 |\n\
+             | x          |       |\n\
+             +------------+-------+\n"
+        );
+        assert_eq!(
+            table
+                .clone()
+                .with_header(false)
+                .to_markdown()
+                .lines()
+                .next(),
+            Some("|  |  |")
+        );
+    }
+
+    #[test]
+    fn from_html() {
+        // Trimmed from what Excel publishes. The merged title leaves a blank
+        // cell, so the first row isn't taken for a header.
+        let html = "<html xmlns:o=\"urn:schemas-microsoft-com:office:office\"><head>\
+            <style>td { mso-number-format: General; }</style></head><body>\
+            <table border=0 cellpadding=0 style='border-collapse:collapse'>\
+            <col width=64 style='width:48pt'>\
+            <tr height=20><td class=xl65 style='mso-height-source:userset'>Region</td>\
+            <td class=xl65 colspan=2 style='mso-ignore:colspan'>Sales</td></tr>\
+            <tr><td rowspan=2>North</td><td align=right x:num>1,200</td><td>a<br style='mso-data-placement:same-cell'>b</td></tr>\
+            <tr><td x:num>&nbsp;3</td><td><span style='mso-hide:all'>hidden</span>c<o:p></o:p></td></tr>\
+            <!--EndFragment--></table></body></html>";
+        assert_eq!(
+            tables_from_html(html),
+            vec![table(&[
+                &["Region", "Sales", ""],
+                &["North", "1,200", "a\nb"],
+                &["", "3", "c"],
+            ])]
+        );
+    }
+
+    #[test]
+    fn from_html_structure() {
+        let html = "<p>before</p>\
+            <table><thead><tr><td>a</td><td>b</td></thead>\
+            <tr><td colspan=\"2\" rowspan=\"9\">wide\n  and   tall<td>x\
+            <tr><td>y<table><tr><td>in</td><td>ner</td></tr></table></td></table>\
+            <table><tr><th>1</th></tr><tr><td>2</td></tr></table>\
+            <table style=\"display: none\"><tr><td>hidden</td></tr></table>\
+            <table><tr><td>unclosed";
+        assert_eq!(
+            tables_from_html(html),
+            vec![
+                table(&[
+                    &["a", "b"],
+                    &["wide and tall", "", "x"],
+                    &["", "", "y\nin ner"]
+                ])
+                .with_header(true),
+                table(&[&["1"], &["2"]]).with_header(true),
+                table(&[&["unclosed"]]),
+            ]
+        );
+        assert_eq!(tables_from_html("<table></table><p>none</p>"), vec![]);
+        assert_eq!(
+            tables_from_html(
+                "<table><tr><td>a</td><td style=\"display:none\"><p>x</td><td>b</td></tr>\
+                 <tr><td>c</td><td>d</td></tr></table>"
+            ),
+            vec![table(&[&["a", "b"], &["c", "d"]])]
+        );
+
+        // Spans and rows that would make a huge grid cut the table off.
+        let html = format!(
+            "<table><tr><td>a<td>b{}</table>",
+            "<tr><td colspan=1000 rowspan=1000>x".repeat(1000)
+        );
+        let tables = tables_from_html(&html);
+        let rows = tables[0].rows();
+        assert_eq!(rows[0], ["a", "b"].map(String::from));
+        assert!(rows.len() * rows[0].len() <= MAX_CELLS);
+        let html = format!("<table>{}</table>", "<tr><td>x".repeat(100_000));
+        assert_eq!(tables_from_html(&html)[0].rows().len(), MAX_CELLS);
+        let html = format!("<table>{}</table>", "<td colspan=1000>".repeat(100_000));
+        assert_eq!(tables_from_html(&html)[0].rows()[0].len(), 65_000);
+    }
+
+    #[test]
+    fn write_table() {
+        let backend = MemoryPasteBoard::new();
+        let board = PasteBoard::with_backend(backend.clone());
+        assert!(matches!(
+            board.get_table(),
+            Err(ClipboardError::TypeUnavailable(Type::TabularText))
+        ));
+
+        let table = table(&[&["Name", "Qty"], &["pear", "3"]]).with_header(true);
+        board.write_table(&table).unwrap();
+        assert_eq!(backend.change_count(), 1);
+        assert_eq!(
+            board.types().unwrap(),
+            vec![Type::TabularText, Type::HTML, Type::String]
+        );
+        assert_eq!(board.get_table().unwrap(), table);
+        assert_eq!(board.get_text_lossy().unwrap(), table.to_ascii());
+
+        // Without tabular text, the first HTML table is read.
+        board
+            .write_contents(Content::String(table.to_html().into()), Type::HTML)
+            .unwrap();
+        assert_eq!(board.get_table().unwrap(), table);
+    }
+}
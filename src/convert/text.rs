@@ -0,0 +1,433 @@
+use super::html::{tokenize, Token};
+use super::{css, Alignment, AttributedText, Color, RunStyle};
+
+/// Elements whose content is never shown.
+pub(crate) const HIDDEN: &[&str] = &["script", "style", "head", "template", "noscript"];
+
+/// Elements laid out on lines of their own.
+pub(crate) const BLOCK: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "section",
+    "summary",
+    "table",
+    "tr",
+    "ul",
+];
+
+/// Block elements that get a blank line around them.
+pub(crate) const PARAGRAPH: &[&str] = &["p", "h1", "h2", "h3", "h4", "h5", "h6", "pre"];
+
+/// Deepest list nesting that indents further, so hostile nesting can't
+/// blow the text up.
+const MAX_DEPTH: usize = 64;
+
+/// Sizes of `h1` to `h6`, in half points.
+const HEADING_SIZES: [u32; 6] = [48, 36, 28, 24, 20, 16];
+
+/// Sizes of `<font size>` 1 to 7, in half points.
+const FONT_SIZES: [u32; 7] = [15, 20, 24, 27, 36, 48, 72];
+
+/// The style of text inside `token`, an element named `name`, nested in
+/// text styled `base`.
+fn element_style(name: &str, token: &Token, base: &RunStyle) -> RunStyle {
+    let mut style = base.clone();
+    match name {
+        "b" | "strong" => style.bold = true,
+        "i" | "em" | "cite" | "dfn" | "var" => style.italic = true,
+        "u" | "ins" => style.underline = true,
+        "s" | "strike" | "del" => style.strikethrough = true,
+        "a" => {
+            if let Some(href) = token.attr("href").filter(|href| !href.is_empty()) {
+                style.link = Some(href.to_string());
+            }
+        }
+        "mark" => style.background = Some(Color::new(255, 255, 0)),
+        "center" => style.alignment = Alignment::Center,
+        "code" | "kbd" | "pre" | "samp" | "tt" => style.font = Some(css::MONOSPACE.to_string()),
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            style.bold = true;
+            style.size = Some(HEADING_SIZES[usize::from(name.as_bytes()[1] - b'1')]);
+        }
+        "font" => {
+            if let Some(face) = token.attr("face").and_then(css::parse_font_family) {
+                style.font = Some(face);
+            }
+            if let Some(color) = token.attr("color").and_then(css::parse_color) {
+                style.color = Some(color);
+            }
+            let size = token
+                .attr("size")
+                .and_then(|size| size.trim().parse::<usize>().ok());
+            if let Some(size) = size.and_then(|size| FONT_SIZES.get(size.checked_sub(1)?)) {
+                style.size = Some(*size);
+            }
+        }
+        _ => {}
+    }
+    if let Some(alignment) = token.attr("align").and_then(parse_alignment) {
+        style.alignment = alignment;
+    }
+    for (property, value) in token.attr("style").into_iter().flat_map(css::declarations) {
+        match property.as_str() {
+            "font-weight" => {
+                style.bold = match value {
+                    "bold" | "bolder" => true,
+                    weight => weight.parse::<u32>().is_ok_and(|weight| weight >= 600),
+                }
+            }
+            "font-style" => style.italic = matches!(value, "italic" | "oblique"),
+            "text-decoration" | "text-decoration-line" => {
+                if value.contains("none") {
+                    style.underline = false;
+                    style.strikethrough = false;
+                }
+                style.underline |= value.contains("underline");
+                style.strikethrough |= value.contains("line-through");
+            }
+            "font-family" => {
+                if let Some(font) = css::parse_font_family(value) {
+                    style.font = Some(font);
+                }
+            }
+            "font-size" => {
+                if let Some(size) = css::parse_font_size(value) {
+                    style.size = Some(size);
+                }
+            }
+            "color" => {
+                if let Some(color) = css::parse_color(value) {
+                    style.color = Some(color);
+                }
+            }
+            "background" | "background-color" => {
+                if let Some(color) = css::parse_color(value) {
+                    style.background = Some(color);
+                }
+            }
+            "text-align" => {
+                if let Some(alignment) = parse_alignment(value) {
+                    style.alignment = alignment;
+                }
+            }
+            _ => {}
+        }
+    }
+    style
+}
+
+fn parse_alignment(value: &str) -> Option<Alignment> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "left" | "start" => Some(Alignment::Left),
+        "center" | "middle" => Some(Alignment::Center),
+        "right" | "end" => Some(Alignment::Right),
+        "justify" => Some(Alignment::Justified),
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+enum List {
+    Unordered,
+    Ordered(u64),
+}
+
+#[derive(Debug, Default)]
+struct Renderer {
+    out: AttributedText,
+    /// Open elements and the style of the text inside them.
+    styles: Vec<(String, RunStyle)>,
+    /// Newlines owed before the next visible text.
+    newlines: usize,
+    /// Whether collapsed whitespace is owed before the next visible text.
+    space: bool,
+    /// Style of the text the owed whitespace came from.
+    space_style: RunStyle,
+    hidden: usize,
+    pre: usize,
+    lists: Vec<List>,
+    /// Cells already written in the current table row.
+    cells: usize,
+}
+
+impl Renderer {
+    fn push(&mut self, text: &str) {
+        match self.styles.last() {
+            Some((_, style)) => self.out.push_str(text, style),
+            None => self.out.push_str(text, &RunStyle::default()),
+        }
+    }
+
+    fn block(&mut self, newlines: usize) {
+        self.newlines = self.newlines.max(newlines);
+        self.space = false;
+    }
+
+    /// End the current line now, making sure at least `min` (or the owed
+    /// number of) newlines separate it from what comes next.
+    fn line_break(&mut self, min: usize) {
+        let newlines = self.newlines.max(min);
+        self.newlines = 0;
+        self.space = false;
+        if self.out.is_empty() {
+            return;
+        }
+        while self.out.text().ends_with([' ', '\t']) {
+            self.out.pop();
+        }
+        let trailing = self.out.len() - self.out.text().trim_end_matches('\n').len();
+        for _ in trailing..newlines {
+            self.out.extend("\n");
+        }
+    }
+
+    /// Settle owed whitespace before visible text.
+    fn flush(&mut self) {
+        if self.newlines > 0 {
+            self.line_break(0);
+            if !self.out.is_empty() {
+                // Keep continuation lines of a list item under its text.
+                self.indent(self.lists.len());
+            }
+        } else if self.space
+            && !self.out.is_empty()
+            && !self.out.text().ends_with([' ', '\n', '\t'])
+        {
+            self.out.push_str(" ", &self.space_style);
+        }
+        self.space = false;
+    }
+
+    fn indent(&mut self, depth: usize) {
+        self.push(&" ".repeat(depth.min(MAX_DEPTH) * 2));
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.hidden > 0 {
+            return;
+        }
+        if self.pre > 0 {
+            self.flush();
+            self.push(text);
+            return;
+        }
+        let style = self.styles.last().map(|(_, style)| style.clone());
+        if text.starts_with(|c: char| c.is_ascii_whitespace()) {
+            self.space = true;
+            self.space_style = style.clone().unwrap_or_default();
+        }
+        let mut words = text.split_ascii_whitespace().peekable();
+        while let Some(word) = words.next() {
+            self.flush();
+            self.push(word);
+            self.space = words.peek().is_some();
+            self.space_style = style.clone().unwrap_or_default();
+        }
+        if text.ends_with(|c: char| c.is_ascii_whitespace()) {
+            self.space = true;
+            self.space_style = style.unwrap_or_default();
+        }
+    }
+
+    fn start(&mut self, name: &str, token: &Token) {
+        let base = self.styles.last().map(|(_, style)| style);
+        let style = element_style(name, token, base.unwrap_or(&RunStyle::default()));
+        self.styles.push((name.to_string(), style));
+        if HIDDEN.contains(&name) {
+            self.hidden += 1;
+            return;
+        }
+        if self.hidden > 0 {
+            return;
+        }
+        match name {
+            "br" => {
+                if self.newlines > 0 {
+                    self.line_break(0);
+                }
+                if !self.out.is_empty() {
+                    self.out.extend("\n");
+                }
+                self.space = false;
+            }
+            "pre" => {
+                self.block(2);
+                self.pre += 1;
+            }
+            "ul" => {
+                self.block(1);
+                self.lists.push(List::Unordered);
+            }
+            "ol" => {
+                self.block(1);
+                let start = token
+                    .attr("start")
+                    .and_then(|start| start.parse().ok())
+                    .unwrap_or(1);
+                self.lists.push(List::Ordered(start));
+            }
+            "li" => {
+                self.line_break(1);
+                let marker = match self.lists.last_mut() {
+                    Some(List::Ordered(n)) => {
+                        let marker = format!("{n}. ");
+                        *n = n.saturating_add(1);
+                        marker
+                    }
+                    _ => "• ".to_string(),
+                };
+                self.indent(self.lists.len().saturating_sub(1));
+                self.push(&marker);
+            }
+            "tr" => {
+                self.block(1);
+                self.cells = 0;
+            }
+            "td" | "th" => {
+                if self.cells > 0 {
+                    self.newlines = 0;
+                    self.space = false;
+                    self.push("\t");
+                }
+                self.cells += 1;
+            }
+            "img" => {
+                if let Some(alt) = token.attr("alt") {
+                    self.text(alt);
+                }
+            }
+            _ if PARAGRAPH.contains(&name) => self.block(2),
+            _ if BLOCK.contains(&name) => self.block(1),
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, name: &str) {
+        if let Some(idx) = self.styles.iter().rposition(|(open, _)| open == name) {
+            self.styles.truncate(idx);
+        }
+        if HIDDEN.contains(&name) {
+            self.hidden = self.hidden.saturating_sub(1);
+            return;
+        }
+        if self.hidden > 0 {
+            return;
+        }
+        match name {
+            "pre" => {
+                self.pre = self.pre.saturating_sub(1);
+                self.block(2);
+            }
+            "ul" | "ol" => {
+                self.lists.pop();
+                self.block(1);
+            }
+            _ if PARAGRAPH.contains(&name) => self.block(2),
+            _ if BLOCK.contains(&name) => self.block(1),
+            _ => {}
+        }
+    }
+}
+
+/// Render HTML as readable plain text.
+///
+/// Block elements start new lines, paragraphs and headings are separated by
+/// blank lines, list items get bullets or numbers, `<pre>` keeps its
+/// whitespace and table cells are separated by tabs. Scripts, styles and
+/// the document head are dropped.
+pub fn html_to_text(html: &str) -> String {
+    render_html(html).text().replace('\u{a0}', " ")
+}
+
+/// Lay out HTML like `html_to_text`, keeping the character formatting.
+pub(crate) fn render_html(html: &str) -> AttributedText {
+    let mut renderer = Renderer::default();
+    for token in tokenize(html) {
+        match &token {
+            Token::Start {
+                name, self_closing, ..
+            } => {
+                renderer.start(name, &token);
+                if *self_closing {
+                    renderer.end(name);
+                }
+            }
+            Token::End { name } => renderer.end(name),
+            Token::Text(text) => renderer.text(text),
+        }
+    }
+    let mut out = renderer.out;
+    out.truncate(out.text().trim_end().len());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks() {
+        assert_eq!(
+            html_to_text("<h1>Title</h1><p>One   two\nthree</p><p>Next <b>bold</b>.</p><div>a</div><div>b</div>"),
+            "Title\n\nOne two three\n\nNext bold.\n\na\nb"
+        );
+        assert_eq!(html_to_text("a<br>b<br><br>c"), "a\nb\n\nc");
+        assert_eq!(html_to_text("<p>x&nbsp;&amp;&nbsp;y &lt;3</p>"), "x & y <3");
+    }
+
+    #[test]
+    fn lists() {
+        let html = "<ul><li>one</li><li>two<ol start=\"3\"><li>three</li><li>four</li></ol></li></ul><p>after</p>";
+        assert_eq!(
+            html_to_text(html),
+            "• one\n• two\n  3. three\n  4. four\n\nafter"
+        );
+        assert_eq!(
+            html_to_text("<ol start=\"18446744073709551615\"><li>a</li><li>b</li></ol>"),
+            "18446744073709551615. a\n18446744073709551615. b"
+        );
+
+        let html = format!("{}{}", "<ul>".repeat(20_000), "<li>x".repeat(20_000));
+        let text = html_to_text(&html);
+        assert_eq!(text.lines().count(), 20_000);
+        assert!(text.lines().all(|line| line.len() <= MAX_DEPTH * 2 + "• x".len()));
+    }
+
+    #[test]
+    fn pre() {
+        assert_eq!(
+            html_to_text("<p>code:</p><pre>fn main() {\n    x  &lt; 1\n}</pre><p>done</p>"),
+            "code:\n\nfn main() {\n    x  < 1\n}\n\ndone"
+        );
+    }
+
+    #[test]
+    fn tables() {
+        let html = "<table><tr><th>Name</th><th>Qty</th></tr>\n<tr><td>apple</td><td>3</td></tr><tr><td> pear </td><td></td></tr></table>";
+        assert_eq!(html_to_text(html), "Name\tQty\napple\t3\npear");
+    }
+
+    #[test]
+    fn hidden() {
+        let html = "<html><head><title>T</title><style>p { color: red }</style></head><body><script>alert(1)</script><p>Visible</p></body></html>";
+        assert_eq!(html_to_text(html), "Visible");
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{ClipboardBackend, ClipboardError, Content, PasteBoard, Type};
+
+/// A caller-owned marker of the last clipboard generation a consumer has
+/// handled.
+///
+/// Cursors are plain values: each reader keeps its own, and reading several
+/// types against the same cursor never invalidates it. Take a fresh one with
+/// `PasteBoard::cursor` once the current contents have been handled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ChangeCursor {
+    change_count: Option<i64>,
+}
+
+impl ChangeCursor {
+    /// A cursor that hasn't seen anything yet, so any content counts as new.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A cursor positioned at `change_count`.
+    pub fn at(change_count: i64) -> Self {
+        Self {
+            change_count: Some(change_count),
+        }
+    }
+
+    pub fn change_count(&self) -> Option<i64> {
+        self.change_count
+    }
+
+    pub(crate) fn is_current(&self, change_count: i64) -> bool {
+        self.change_count == Some(change_count)
+    }
+}
+
+impl<B: ClipboardBackend> PasteBoard<B> {
+    pub fn change_count(&self) -> i64 {
+        self.board.change_count()
+    }
+
+    /// A cursor at the current change count.
+    pub fn cursor(&self) -> ChangeCursor {
+        ChangeCursor::at(self.change_count())
+    }
+
+    /// Whether the clipboard changed after `cursor` was taken.
+    pub fn has_changed_since(&self, cursor: &ChangeCursor) -> bool {
+        !cursor.is_current(self.change_count())
+    }
+
+    /// Read `ty`, or fail with `ClipboardError::NoNewContent` if the
+    /// clipboard hasn't changed since `cursor`.
+    pub fn get_contents_since(
+        &self,
+        cursor: &ChangeCursor,
+        ty: Type,
+    ) -> Result<Content, ClipboardError> {
+        if !self.has_changed_since(cursor) {
+            return Err(ClipboardError::NoNewContent);
+        }
+        self.board.get_contents(ty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryPasteBoard;
+
+    #[test]
+    fn independent_types() {
+        let board = PasteBoard::with_backend(MemoryPasteBoard::new());
+        let cursor = ChangeCursor::new();
+        board
+            .writer()
+            .add(Type::String, Content::String("a".into()))
+            .add(Type::HTML, Content::String("<b>a</b>".into()))
+            .commit()
+            .unwrap();
+
+        assert!(board.get_contents_since(&cursor, Type::String).is_ok());
+        assert!(board.get_contents_since(&cursor, Type::HTML).is_ok());
+
+        let cursor = board.cursor();
+        assert_eq!(cursor.change_count(), Some(1));
+        assert!(matches!(
+            board.get_contents_since(&cursor, Type::String),
+            Err(ClipboardError::NoNewContent)
+        ));
+    }
+
+    #[test]
+    fn independent_readers() {
+        let board = PasteBoard::with_backend(MemoryPasteBoard::new());
+        board
+            .write_contents(Content::String("a".into()), Type::String)
+            .unwrap();
+        let first = board.cursor();
+        board
+            .write_contents(Content::String("b".into()), Type::String)
+            .unwrap();
+        let second = board.cursor();
+
+        assert!(board.has_changed_since(&first));
+        assert!(!board.has_changed_since(&second));
+        match board.get_contents_since(&first, Type::String).unwrap() {
+            Content::String(val) => assert_eq!(&*val, "b"),
+            _ => panic!("Get incorrect value."),
+        }
+        assert!(board.get_contents_since(&second, Type::String).is_err());
+    }
+}
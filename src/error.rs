@@ -0,0 +1,87 @@
+use std::fmt;
+
+use crate::Type;
+
+#[derive(Debug)]
+pub enum ClipboardError {
+    /// The change count has not moved since the last read.
+    NoNewContent,
+    /// The pasteboard holds nothing of the requested type.
+    TypeUnavailable(Type),
+    /// The type can't be read or written by this crate.
+    UnsupportedType(Type),
+    /// Foundation failed to allocate the named object.
+    AllocationFailed(&'static str),
+    /// The pasteboard refused to take the content.
+    WriteRejected(Type),
+    /// The content doesn't look like its declared type: refused by a strict
+    /// write, or an image whose header can't be read.
+    ContentMismatch(Type),
+    /// Any other failure reported by the backend.
+    Backend(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl fmt::Display for ClipboardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoNewContent => write!(f, "There is no newer content to get."),
+            Self::TypeUnavailable(ty) => write!(f, "No {:?} content on the clipboard.", ty),
+            Self::UnsupportedType(ty) => write!(f, "Unsupported clipboard type {:?}.", ty),
+            Self::AllocationFailed(what) => write!(f, "Fail to init {}.", what),
+            Self::WriteRejected(ty) => write!(f, "Fail to set {:?} content to clipboard.", ty),
+            Self::ContentMismatch(ty) => write!(f, "Content doesn't look like {:?}.", ty),
+            Self::Backend(err) => write!(f, "Clipboard backend error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ClipboardError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Backend(err) => Some(&**err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ClipboardError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Backend(Box::new(err))
+    }
+}
+
+impl From<&str> for ClipboardError {
+    fn from(msg: &str) -> Self {
+        Self::Backend(msg.into())
+    }
+}
+
+impl From<String> for ClipboardError {
+    fn from(msg: String) -> Self {
+        Self::Backend(msg.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn send_sync() {
+        fn assert_send_sync<T: Send + Sync + std::error::Error>() {}
+        assert_send_sync::<ClipboardError>();
+    }
+
+    #[test]
+    fn backend_source() {
+        use std::error::Error;
+
+        let err = ClipboardError::from("Can't get generalPasteboard");
+        assert!(err.source().is_some());
+        assert_eq!(
+            err.to_string(),
+            "Clipboard backend error: Can't get generalPasteboard"
+        );
+        assert!(ClipboardError::NoNewContent.source().is_none());
+    }
+}
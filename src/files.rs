@@ -0,0 +1,215 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::{ClipboardBackend, ClipboardError, Content, PasteBoard, Type};
+
+impl<B: ClipboardBackend> PasteBoard<B> {
+    /// Paths of the copied files, one per pasteboard item. URLs that don't
+    /// name a local file are skipped.
+    pub fn get_files(&self) -> Result<Vec<PathBuf>, ClipboardError> {
+        let urls = self.board.item_contents(Type::FileUrl)?;
+        Ok(urls
+            .iter()
+            .filter_map(Content::as_str)
+            .filter_map(url_to_path)
+            .collect())
+    }
+
+    /// Publish `paths` as file URLs, one pasteboard item each, with the
+    /// paths as lines of `Type::String` for text editors. Relative paths are
+    /// taken against the current directory.
+    pub fn write_files<P: AsRef<Path>>(&self, paths: &[P]) -> Result<(), ClipboardError> {
+        let mut items = Vec::with_capacity(paths.len());
+        let mut lines = Vec::with_capacity(paths.len());
+        for path in paths {
+            let path = std::path::absolute(path)?;
+            items.push(vec![(
+                Type::FileUrl,
+                Content::String(path_to_url(&path).into()),
+            )]);
+            lines.push(path.to_string_lossy().into_owned());
+        }
+        if let Some(first) = items.first_mut() {
+            first.push((Type::String, Content::String(lines.join("\n").into())));
+        }
+        self.publish_items(items)
+    }
+}
+
+/// A `file://` URL for the absolute `path`, percent-encoding every byte
+/// that isn't allowed in a URL path, as `NSURL` does.
+fn path_to_url(path: &Path) -> String {
+    let mut url = String::from("file://");
+    for &byte in path_bytes(path).iter() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' => url.push(byte as char),
+            b'-' | b'.' | b'_' | b'~' | b'/' | b'!' | b'$' | b'&' | b'\'' | b'(' | b')' | b'*'
+            | b'+' | b',' | b';' | b'=' | b':' | b'@' => url.push(byte as char),
+            _ => url.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    url
+}
+
+/// The local path named by a `file://` URL, with or without a
+/// `localhost` host.
+fn url_to_path(url: &str) -> Option<PathBuf> {
+    let scheme = url.get(..7)?;
+    if !scheme.eq_ignore_ascii_case("file://") {
+        return None;
+    }
+    let rest = &url[7..];
+    let rest = rest.strip_prefix("localhost").unwrap_or(rest);
+    if !rest.starts_with('/') {
+        return None;
+    }
+    // An unescaped `?` or `#` starts the query or fragment.
+    let encoded = rest.split(['?', '#']).next().unwrap_or_default();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut idx = 0;
+    while idx < encoded.len() {
+        let byte = encoded.as_bytes()[idx];
+        let escaped = encoded
+            .get(idx + 1..idx + 3)
+            .filter(|_| byte == b'%')
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(escaped) => {
+                bytes.push(escaped);
+                idx += 3;
+            }
+            None => {
+                bytes.push(byte);
+                idx += 1;
+            }
+        }
+    }
+    // Finder ends directory URLs with a slash.
+    if bytes.len() > 1 && bytes.ends_with(b"/") {
+        bytes.pop();
+    }
+    Some(PathBuf::from(path_from_bytes(bytes)))
+}
+
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> std::borrow::Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().into()
+}
+
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> std::borrow::Cow<'_, [u8]> {
+    match path.to_string_lossy() {
+        std::borrow::Cow::Borrowed(path) => path.as_bytes().into(),
+        std::borrow::Cow::Owned(path) => path.into_bytes().into(),
+    }
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> OsString {
+    use std::os::unix::ffi::OsStringExt;
+    OsString::from_vec(bytes)
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>) -> OsString {
+    String::from_utf8_lossy(&bytes).into_owned().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::MemoryPasteBoard;
+
+    #[test]
+    fn urls() {
+        assert_eq!(
+            path_to_url(Path::new("/Users/me/My Files/#1 50%/naïve.txt")),
+            "file:///Users/me/My%20Files/%231%2050%25/na%C3%AFve.txt"
+        );
+        assert_eq!(
+            url_to_path("file:///Users/me/My%20Files/na%C3%AFve.txt"),
+            Some(PathBuf::from("/Users/me/My Files/naïve.txt"))
+        );
+        assert_eq!(
+            url_to_path("FILE://localhost/Applications/Safari.app/"),
+            Some(PathBuf::from("/Applications/Safari.app"))
+        );
+        assert_eq!(
+            url_to_path("file:///a%2/b%zz?q#f"),
+            Some(PathBuf::from("/a%2/b%zz"))
+        );
+        assert_eq!(url_to_path("file:///"), Some(PathBuf::from("/")));
+        assert_eq!(url_to_path("file://server/share"), None);
+        assert_eq!(url_to_path("https://example.com/a"), None);
+    }
+
+    #[test]
+    fn several_files() {
+        let board = PasteBoard::with_backend(MemoryPasteBoard::new());
+        assert!(matches!(
+            board.get_files(),
+            Err(ClipboardError::TypeUnavailable(Type::FileUrl))
+        ));
+
+        let paths = [
+            PathBuf::from("/tmp/a b.txt"),
+            PathBuf::from("/tmp/日本/c#d"),
+        ];
+        board.write_files(&paths).unwrap();
+        assert_eq!(board.backend().change_count(), 1);
+        assert_eq!(board.get_files().unwrap(), paths);
+        assert_eq!(
+            board.get_text_lossy().unwrap(),
+            "/tmp/a b.txt\n/tmp/日本/c#d"
+        );
+
+        board.write_files(&["relative"]).unwrap();
+        assert_eq!(
+            board.get_files().unwrap(),
+            vec![std::env::current_dir().unwrap().join("relative")]
+        );
+
+        board
+            .write_contents(Content::String("https://example.com".into()), Type::FileUrl)
+            .unwrap();
+        assert!(board.get_files().unwrap().is_empty());
+    }
+
+    /// Absolute paths built from arbitrary segments.
+    fn paths() -> impl Strategy<Value = Vec<PathBuf>> {
+        let segment = prop_oneof![
+            "[^/\\x00]{1,12}",
+            "[ #%?&+:;=@a-z.\u{e9}\u{65e5}\u{1f600}]{1,8}",
+        ];
+        let path = prop::collection::vec(segment, 1..5)
+            .prop_map(|segments| PathBuf::from(format!("/{}", segments.join("/"))));
+        prop::collection::vec(path, 1..4)
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(paths in paths()) {
+            let board = PasteBoard::with_backend(MemoryPasteBoard::new());
+            board.write_files(&paths).unwrap();
+            prop_assert_eq!(board.get_files().unwrap(), paths);
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn round_trip_bytes(segments in prop::collection::vec(
+            prop::collection::vec(any::<u8>().prop_filter("separator", |b| *b != b'/' && *b != 0), 1..12),
+            1..5,
+        )) {
+            let mut bytes = Vec::new();
+            for segment in segments {
+                bytes.push(b'/');
+                bytes.extend(segment);
+            }
+            let path = PathBuf::from(path_from_bytes(bytes));
+            prop_assert_eq!(url_to_path(&path_to_url(&path)), Some(path));
+        }
+    }
+}
@@ -0,0 +1,541 @@
+//! A clipboard history that survives restarts.
+//!
+//! Entries are deduplicated by content, ordered by last use and evicted
+//! least recently used first once either limit in `HistoryOptions` is hit.
+//! A persistent history keeps one `.clipsnap` file per entry plus an
+//! `index` file recording the order and timestamps.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{
+    clipsnap, ClipboardBackend, ClipboardError, ClipboardEvent, ClipboardSnapshot, Content,
+    PasteBoard, PrivacyMarkers,
+};
+
+const INDEX: &str = "index";
+
+#[derive(Debug, Clone)]
+pub struct HistoryOptions {
+    max_entries: usize,
+    max_bytes: u64,
+    record_private: bool,
+}
+
+impl Default for HistoryOptions {
+    fn default() -> Self {
+        Self {
+            max_entries: 200,
+            max_bytes: 64 * 1024 * 1024,
+            record_private: false,
+        }
+    }
+}
+
+impl HistoryOptions {
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Upper bound on the summed size of every stored representation.
+    pub fn max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Also record content carrying privacy markers, such as passwords
+    /// copied from a password manager. Off by default; when on, such
+    /// entries can be told apart by `HistoryEntry::privacy_markers`.
+    pub fn record_private(mut self, record: bool) -> Self {
+        self.record_private = record;
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    id: u64,
+    /// `content_hash` of the snapshot, which `id` starts from.
+    hash: u64,
+    snapshot: ClipboardSnapshot,
+    created: SystemTime,
+    last_used: SystemTime,
+}
+
+impl HistoryEntry {
+    /// Content hash identifying the entry, moved on to the next free value
+    /// when different contents collide; equal contents share an id.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn snapshot(&self) -> &ClipboardSnapshot {
+        &self.snapshot
+    }
+
+    /// When these contents were first copied.
+    pub fn created(&self) -> SystemTime {
+        self.created
+    }
+
+    /// When these contents were last copied or touched.
+    pub fn last_used(&self) -> SystemTime {
+        self.last_used
+    }
+
+    /// Bytes taken by the representations, not counting type tags.
+    pub fn size(&self) -> u64 {
+        snapshot_size(&self.snapshot)
+    }
+
+    pub fn privacy_markers(&self) -> PrivacyMarkers {
+        self.snapshot.privacy_markers()
+    }
+}
+
+fn snapshot_size(snapshot: &ClipboardSnapshot) -> u64 {
+    snapshot
+        .items()
+        .iter()
+        .map(|(_, content)| match content {
+            Content::Data(data) => data.len() as u64,
+            Content::String(string) => string.len() as u64,
+            Content::Attributed(text) => text.len() as u64,
+        })
+        .sum()
+}
+
+/// 64-bit FNV-1a over the `.clipsnap` encoding, which is stable across
+/// releases unlike `std`'s hasher.
+fn content_hash(snapshot: &ClipboardSnapshot) -> u64 {
+    clipsnap::to_vec(snapshot)
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+#[derive(Debug)]
+pub struct ClipboardHistory {
+    dir: Option<PathBuf>,
+    options: HistoryOptions,
+    /// Most recently used first.
+    entries: Vec<HistoryEntry>,
+}
+
+impl ClipboardHistory {
+    /// A history that lives only as long as the value.
+    pub fn in_memory(options: HistoryOptions) -> Self {
+        Self {
+            dir: None,
+            options,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Load the history stored in `dir`, creating the directory if needed.
+    ///
+    /// Entries whose file went missing or no longer parses are dropped.
+    pub fn open(dir: impl AsRef<Path>, options: HistoryOptions) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let index = match fs::read_to_string(dir.join(INDEX)) {
+            Ok(index) => index,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+
+        let mut entries = Vec::new();
+        for line in index.lines() {
+            let mut fields = line.split('\t');
+            let (id, created, last_used) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(id), Some(created), Some(last_used)) => (id, created, last_used),
+                _ => continue,
+            };
+            let (id, created, last_used) = match (
+                u64::from_str_radix(id, 16),
+                created.parse::<u64>(),
+                last_used.parse::<u64>(),
+            ) {
+                (Ok(id), Ok(created), Ok(last_used)) => (id, created, last_used),
+                _ => continue,
+            };
+            let snapshot = match fs::File::open(entry_path(&dir, id)).and_then(clipsnap::read) {
+                Ok(snapshot) => snapshot,
+                Err(_) => continue,
+            };
+            entries.push(HistoryEntry {
+                id,
+                hash: content_hash(&snapshot),
+                snapshot,
+                created: UNIX_EPOCH + Duration::from_millis(created),
+                last_used: UNIX_EPOCH + Duration::from_millis(last_used),
+            });
+        }
+
+        let mut history = Self {
+            dir: Some(dir),
+            options,
+            entries,
+        };
+        history.evict();
+        history.sync()?;
+        Ok(history)
+    }
+
+    /// Record `snapshot` as copied at `timestamp` and return its id.
+    ///
+    /// Contents already in the history only get their `last_used` bumped.
+    /// Empty snapshots, ones larger than `max_bytes` and, unless
+    /// `record_private` is set, ones with privacy markers are not stored.
+    pub fn insert(
+        &mut self,
+        snapshot: ClipboardSnapshot,
+        timestamp: SystemTime,
+    ) -> io::Result<Option<u64>> {
+        if snapshot.is_empty()
+            || snapshot_size(&snapshot) > self.options.max_bytes
+            || (!self.options.record_private && snapshot.privacy_markers().is_private())
+        {
+            return Ok(None);
+        }
+        // `remove` and eviction leave gaps in a chain of colliding ids, so
+        // look for equal contents everywhere rather than probing from the hash.
+        let hash = content_hash(&snapshot);
+        let existing = self
+            .entries
+            .iter()
+            .position(|entry| entry.hash == hash && entry.snapshot == snapshot);
+        let entry = match existing {
+            Some(idx) => {
+                let mut entry = self.entries.remove(idx);
+                entry.last_used = entry.last_used.max(timestamp);
+                entry
+            }
+            None => {
+                let mut id = hash;
+                while self.entries.iter().any(|entry| entry.id == id) {
+                    id = id.wrapping_add(1);
+                }
+                if let Some(dir) = &self.dir {
+                    write_atomic(&entry_path(dir, id), &clipsnap::to_vec(&snapshot))?;
+                }
+                HistoryEntry {
+                    id,
+                    hash,
+                    snapshot,
+                    created: timestamp,
+                    last_used: timestamp,
+                }
+            }
+        };
+        let id = entry.id;
+        self.entries.insert(0, entry);
+        self.evict();
+        self.sync()?;
+        Ok(Some(id))
+    }
+
+    /// Snapshot `board` for the change reported by `event`. Private changes
+    /// that won't be recorded aren't read at all.
+    pub fn capture<B: ClipboardBackend>(
+        &mut self,
+        board: &PasteBoard<B>,
+        event: &ClipboardEvent,
+    ) -> Result<Option<u64>, ClipboardError> {
+        if !self.options.record_private && event.markers.is_private() {
+            return Ok(None);
+        }
+        let snapshot = board.snapshot()?;
+        Ok(self.insert(snapshot, event.timestamp)?)
+    }
+
+    /// Mark the entry as used now, e.g. after pasting it back.
+    pub fn touch(&mut self, id: u64) -> io::Result<bool> {
+        let idx = match self.entries.iter().position(|entry| entry.id == id) {
+            Some(idx) => idx,
+            None => return Ok(false),
+        };
+        let mut entry = self.entries.remove(idx);
+        entry.last_used = SystemTime::now();
+        self.entries.insert(0, entry);
+        self.sync()?;
+        Ok(true)
+    }
+
+    pub fn get(&self, id: u64) -> Option<&HistoryEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    pub fn remove(&mut self, id: u64) -> io::Result<bool> {
+        let len = self.entries.len();
+        self.entries.retain(|entry| entry.id != id);
+        if self.entries.len() == len {
+            return Ok(false);
+        }
+        self.sync()?;
+        Ok(true)
+    }
+
+    pub fn clear(&mut self) -> io::Result<()> {
+        self.entries.clear();
+        self.sync()
+    }
+
+    /// Entries, most recently used first.
+    pub fn iter(&self) -> impl Iterator<Item = &HistoryEntry> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.entries.iter().map(HistoryEntry::size).sum()
+    }
+
+    fn evict(&mut self) {
+        self.entries.truncate(self.options.max_entries);
+        let mut total = self.total_bytes();
+        while total > self.options.max_bytes {
+            match self.entries.pop() {
+                Some(entry) => total -= entry.size(),
+                None => break,
+            }
+        }
+    }
+
+    /// Rewrite the index and delete files of entries no longer listed.
+    fn sync(&self) -> io::Result<()> {
+        let dir = match &self.dir {
+            Some(dir) => dir,
+            None => return Ok(()),
+        };
+        let mut index = String::new();
+        for entry in &self.entries {
+            index.push_str(&format!(
+                "{:016x}\t{}\t{}\n",
+                entry.id,
+                millis(entry.created),
+                millis(entry.last_used)
+            ));
+        }
+        write_atomic(&dir.join(INDEX), index.as_bytes())?;
+
+        for file in fs::read_dir(dir)? {
+            let path = file?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(clipsnap::EXTENSION) {
+                continue;
+            }
+            let listed = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| u64::from_str_radix(stem, 16).ok())
+                .is_some_and(|id| self.get(id).is_some());
+            if !listed {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn entry_path(dir: &Path, id: u64) -> PathBuf {
+    dir.join(format!("{:016x}.{}", id, clipsnap::EXTENSION))
+}
+
+fn millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as u64)
+}
+
+fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    fs::rename(tmp, path)?;
+    // Make the rename itself durable.
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        fs::File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{MemoryPasteBoard, Type};
+
+    fn text(text: &str) -> ClipboardSnapshot {
+        ClipboardSnapshot::new(vec![(Type::String, Content::String(text.into()))])
+    }
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn temp_dir() -> PathBuf {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "rich-clipboard-history-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn dedup() {
+        let mut history = ClipboardHistory::in_memory(HistoryOptions::default());
+        let a = history.insert(text("a"), at(1)).unwrap().unwrap();
+        history.insert(text("b"), at(2)).unwrap();
+        assert_eq!(history.insert(text("a"), at(3)).unwrap(), Some(a));
+
+        assert_eq!(history.len(), 2);
+        let first = history.iter().next().unwrap();
+        assert_eq!(first.id(), a);
+        assert_eq!(first.created(), at(1));
+        assert_eq!(first.last_used(), at(3));
+        assert_eq!(
+            history.insert(ClipboardSnapshot::default(), at(4)).unwrap(),
+            None
+        );
+
+        // Different contents that hash alike are both kept.
+        let c = content_hash(&text("c"));
+        history.entries[0].id = c;
+        assert_eq!(history.insert(text("c"), at(5)).unwrap(), Some(c + 1));
+        assert_eq!(history.get(c).unwrap().snapshot(), &text("a"));
+        assert_eq!(history.get(c + 1).unwrap().snapshot(), &text("c"));
+        assert_eq!(history.insert(text("c"), at(6)).unwrap(), Some(c + 1));
+        assert_eq!(history.len(), 3);
+    }
+
+    #[test]
+    fn dedup_after_remove() {
+        let mut history = ClipboardHistory::in_memory(HistoryOptions::default());
+        let c = content_hash(&text("c"));
+        history.insert(text("a"), at(1)).unwrap();
+        history.entries[0].id = c;
+        history.insert(text("b"), at(2)).unwrap();
+        history.entries[0].id = c + 1;
+        assert_eq!(history.insert(text("c"), at(3)).unwrap(), Some(c + 2));
+
+        // Removing the head of the chain leaves a gap before "c".
+        assert!(history.remove(c).unwrap());
+        assert_eq!(history.insert(text("c"), at(4)).unwrap(), Some(c + 2));
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.get(c + 2).unwrap().created(), at(3));
+
+        // New colliding contents take the free slot.
+        history.entries[1].id = content_hash(&text("d"));
+        let d = history.insert(text("d"), at(5)).unwrap().unwrap();
+        assert_eq!(d, content_hash(&text("d")) + 1);
+        assert_eq!(history.len(), 3);
+    }
+
+    #[test]
+    fn limits() {
+        let options = HistoryOptions::default().max_entries(3).max_bytes(10);
+        let mut history = ClipboardHistory::in_memory(options);
+        let a = history.insert(text("a"), at(1)).unwrap().unwrap();
+        let b = history.insert(text("b"), at(2)).unwrap().unwrap();
+        history.insert(text("c"), at(3)).unwrap();
+        assert!(history.touch(a).unwrap());
+        history.insert(text("d"), at(4)).unwrap();
+        assert!(history.get(a).is_some());
+        assert!(history.get(b).is_none());
+        assert_eq!(history.len(), 3);
+
+        history.insert(text("0123456789"), at(5)).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history.total_bytes(), 10);
+        assert_eq!(history.insert(text("0123456789a"), at(6)).unwrap(), None);
+    }
+
+    #[test]
+    fn persistent() {
+        let dir = temp_dir();
+        let options = HistoryOptions::default().max_entries(2);
+        let mut history = ClipboardHistory::open(&dir, options.clone()).unwrap();
+        history.insert(text("a"), at(1)).unwrap();
+        let b = history.insert(text("b"), at(2)).unwrap().unwrap();
+        let c = history.insert(text("c"), at(3)).unwrap().unwrap();
+        drop(history);
+
+        let history = ClipboardHistory::open(&dir, options).unwrap();
+        let ids: Vec<_> = history.iter().map(HistoryEntry::id).collect();
+        assert_eq!(ids, vec![c, b]);
+        assert_eq!(history.get(b).unwrap().snapshot(), &text("b"));
+        assert_eq!(history.get(b).unwrap().last_used(), at(2));
+        let files = fs::read_dir(&dir).unwrap().count();
+        assert_eq!(files, 3);
+
+        let history = ClipboardHistory::open(&dir, HistoryOptions::default().max_entries(1));
+        assert_eq!(history.unwrap().len(), 1);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn capture() {
+        let board = PasteBoard::with_backend(MemoryPasteBoard::new());
+        board
+            .write_contents(Content::String("a".into()), Type::String)
+            .unwrap();
+        let event = ClipboardEvent {
+            change_count: board.change_count(),
+            types: board.types().unwrap(),
+            timestamp: at(1),
+            markers: PrivacyMarkers::default(),
+        };
+        let mut history = ClipboardHistory::in_memory(HistoryOptions::default());
+        let id = history.capture(&board, &event).unwrap().unwrap();
+        assert_eq!(history.get(id).unwrap().snapshot(), &text("a"));
+    }
+
+    #[test]
+    fn private() {
+        let secret = ClipboardSnapshot::new(vec![
+            (Type::String, Content::String("hunter2".into())),
+            (
+                Type::from_uti(crate::TRANSIENT_TYPE),
+                Content::Data(Box::new([])),
+            ),
+        ]);
+        let mut history = ClipboardHistory::in_memory(HistoryOptions::default());
+        assert_eq!(history.insert(secret.clone(), at(1)).unwrap(), None);
+        assert!(history.is_empty());
+
+        let event = ClipboardEvent {
+            change_count: 1,
+            types: vec![Type::String],
+            timestamp: at(2),
+            markers: PrivacyMarkers {
+                concealed: true,
+                ..PrivacyMarkers::default()
+            },
+        };
+        // Skipped from the event alone, without reading the clipboard.
+        let board = PasteBoard::with_backend(MemoryPasteBoard::new());
+        assert_eq!(history.capture(&board, &event).unwrap(), None);
+
+        let mut history =
+            ClipboardHistory::in_memory(HistoryOptions::default().record_private(true));
+        let id = history.insert(secret, at(1)).unwrap().unwrap();
+        assert!(history.get(id).unwrap().privacy_markers().transient);
+    }
+}
@@ -0,0 +1,289 @@
+use std::io::Cursor;
+
+use png::{BitDepth, Transformations};
+use tiff::decoder::{Decoder as TiffDecoder, DecodingResult};
+use tiff::encoder::{colortype, Compression, Predictor, TiffEncoder};
+use tiff::tags::{ExtraSamples, Tag};
+
+use crate::{ClipboardBackend, ClipboardError, Content, PasteBoard, Type};
+
+/// A decoded bitmap: 8-bit RGBA samples, row by row, with straight (not
+/// premultiplied) alpha.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+}
+
+impl Image {
+    /// Wrap `rgba` samples, which must hold exactly `width * height` pixels.
+    pub fn new(width: u32, height: u32, rgba: Vec<u8>) -> Result<Self, ClipboardError> {
+        if Some(rgba.len()) != pixels(width, height).and_then(|n| n.checked_mul(4)) {
+            return Err(format!(
+                "{} bytes don't make a {width}x{height} RGBA image",
+                rgba.len()
+            )
+            .into());
+        }
+        Ok(Self {
+            width,
+            height,
+            rgba,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    pub fn into_rgba(self) -> Vec<u8> {
+        self.rgba
+    }
+
+    pub fn from_png(data: &[u8]) -> Result<Self, ClipboardError> {
+        let mut decoder = png::Decoder::new(Cursor::new(data));
+        // Palettes, low bit depths and 16-bit samples all come out as 8-bit
+        // gray or RGB, with or without alpha.
+        decoder.set_transformations(Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(backend)?;
+        let size = reader
+            .output_buffer_size()
+            .ok_or("PNG is too large to decode")?;
+        let mut buf = vec![0; size];
+        let info = reader.next_frame(&mut buf).map_err(backend)?;
+        buf.truncate(info.buffer_size());
+        if info.bit_depth != BitDepth::Eight {
+            return Err(format!("Unexpected {:?} PNG output", info.bit_depth).into());
+        }
+        let channels = info.color_type.samples();
+        Self::new(info.width, info.height, to_rgba(&buf, channels)?)
+    }
+
+    pub fn from_tiff(data: &[u8]) -> Result<Self, ClipboardError> {
+        use tiff::ColorType;
+
+        let mut decoder = TiffDecoder::new(Cursor::new(data)).map_err(backend)?;
+        let (width, height) = decoder.dimensions().map_err(backend)?;
+        let channels = match decoder.colortype().map_err(backend)? {
+            ColorType::Gray(8 | 16) => 1,
+            ColorType::GrayA(8 | 16) => 2,
+            ColorType::RGB(8 | 16) => 3,
+            ColorType::RGBA(8 | 16) => 4,
+            ColorType::CMYK(8) => 5,
+            _ => return Err(ClipboardError::UnsupportedType(Type::TIFF)),
+        };
+        let associated = decoder
+            .find_tag_unsigned_vec::<u16>(Tag::ExtraSamples)
+            .map_err(backend)?
+            .is_some_and(|extra| extra.first() == Some(&ExtraSamples::AssociatedAlpha.to_u16()));
+        let samples = match decoder.read_image().map_err(backend)? {
+            DecodingResult::U8(samples) => samples,
+            DecodingResult::U16(samples) => samples.iter().map(|s| (s >> 8) as u8).collect(),
+            _ => return Err(ClipboardError::UnsupportedType(Type::TIFF)),
+        };
+        let mut rgba = if channels == 5 {
+            to_rgba(&cmyk_to_rgb(&samples), 3)?
+        } else {
+            to_rgba(&samples, channels)?
+        };
+        if associated && matches!(channels, 2 | 4) {
+            unpremultiply(&mut rgba);
+        }
+        Self::new(width, height, rgba)
+    }
+
+    pub fn to_png(&self) -> Result<Vec<u8>, ClipboardError> {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(backend)?;
+        writer.write_image_data(&self.rgba).map_err(backend)?;
+        writer.finish().map_err(backend)?;
+        Ok(png)
+    }
+
+    pub fn to_tiff(&self) -> Result<Vec<u8>, ClipboardError> {
+        let mut tiff = Cursor::new(Vec::new());
+        TiffEncoder::new(&mut tiff)
+            .map_err(backend)?
+            .with_compression(Compression::Lzw)
+            .with_predictor(Predictor::Horizontal)
+            .write_image::<colortype::RGBA8>(self.width, self.height, &self.rgba)
+            .map_err(backend)?;
+        Ok(tiff.into_inner())
+    }
+}
+
+impl<B: ClipboardBackend> PasteBoard<B> {
+    /// The clipboard image decoded from `Type::PNG`, or from `Type::TIFF`
+    /// when no PNG was published.
+    pub fn get_image(&self) -> Result<Image, ClipboardError> {
+        match self.board.get_contents(Type::PNG) {
+            Ok(png) => return Image::from_png(png.as_bytes()),
+            Err(ClipboardError::TypeUnavailable(_)) => {}
+            Err(err) => return Err(err),
+        }
+        match self.board.get_contents(Type::TIFF) {
+            Ok(tiff) => Image::from_tiff(tiff.as_bytes()),
+            Err(ClipboardError::TypeUnavailable(_)) => {
+                Err(ClipboardError::TypeUnavailable(Type::PNG))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Publish `image` as both `Type::PNG` and `Type::TIFF` in one
+    /// transaction, so apps that only read either one can paste it.
+    pub fn write_image(&self, image: &Image) -> Result<(), ClipboardError> {
+        let png = image.to_png()?;
+        let tiff = image.to_tiff()?;
+        self.writer()
+            .add(Type::PNG, Content::Data(png.into()))
+            .add(Type::TIFF, Content::Data(tiff.into()))
+            .commit()
+    }
+}
+
+fn backend<E: std::error::Error + Send + Sync + 'static>(err: E) -> ClipboardError {
+    ClipboardError::Backend(Box::new(err))
+}
+
+fn pixels(width: u32, height: u32) -> Option<usize> {
+    usize::try_from(width)
+        .ok()?
+        .checked_mul(usize::try_from(height).ok()?)
+}
+
+/// Expand gray, gray with alpha, RGB or RGBA samples to RGBA.
+fn to_rgba(samples: &[u8], channels: usize) -> Result<Vec<u8>, ClipboardError> {
+    if !(1..=4).contains(&channels) || !samples.len().is_multiple_of(channels) {
+        return Err("Truncated image data".into());
+    }
+    let mut rgba = Vec::with_capacity(samples.len() / channels * 4);
+    for pixel in samples.chunks_exact(channels) {
+        match *pixel {
+            [gray] => rgba.extend([gray, gray, gray, 255]),
+            [gray, alpha] => rgba.extend([gray, gray, gray, alpha]),
+            [r, g, b] => rgba.extend([r, g, b, 255]),
+            _ => rgba.extend_from_slice(pixel),
+        }
+    }
+    Ok(rgba)
+}
+
+/// Turn premultiplied RGBA, as TIFFs with associated alpha store it, into
+/// straight alpha.
+fn unpremultiply(rgba: &mut [u8]) {
+    for pixel in rgba.chunks_exact_mut(4) {
+        let alpha = u16::from(pixel[3]);
+        if alpha == 0 || alpha == 255 {
+            continue;
+        }
+        for channel in &mut pixel[..3] {
+            let straight = (u16::from(*channel) * 255 + alpha / 2) / alpha;
+            *channel = straight.min(255) as u8;
+        }
+    }
+}
+
+/// Naive CMYK to RGB, as used when there is no color profile to go by.
+fn cmyk_to_rgb(samples: &[u8]) -> Vec<u8> {
+    samples
+        .chunks_exact(4)
+        .flat_map(|cmyk| {
+            let k = 255 - u16::from(cmyk[3]);
+            cmyk[..3]
+                .iter()
+                .map(move |&c| ((255 - u16::from(c)) * k / 255) as u8)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryPasteBoard;
+
+    fn fixture(name: &str) -> Vec<u8> {
+        let path = format!("{}/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+        std::fs::read(path).unwrap()
+    }
+
+    const RGB: [u8; 24] = [
+        255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, //
+        255, 255, 255, 255, 0, 0, 0, 255, 128, 64, 32, 255,
+    ];
+
+    #[test]
+    fn decode() {
+        let png = Image::from_png(&fixture("rgb.png")).unwrap();
+        assert_eq!((png.width(), png.height()), (3, 2));
+        assert_eq!(png.rgba(), RGB);
+        assert_eq!(Image::from_tiff(&fixture("rgb.tiff")).unwrap(), png);
+
+        let palette = Image::from_png(&fixture("palette.png")).unwrap();
+        assert_eq!(
+            palette.rgba(),
+            [255, 0, 0, 0, 0, 0, 255, 255, 0, 0, 255, 255, 255, 0, 0, 0]
+        );
+        let gray = Image::from_png(&fixture("gray_alpha16.png")).unwrap();
+        assert_eq!(gray.rgba(), [255, 255, 255, 128, 18, 18, 18, 255]);
+        let gray = Image::from_tiff(&fixture("gray16.tiff")).unwrap();
+        assert_eq!(gray.rgba(), [255, 255, 255, 255, 128, 128, 128, 255]);
+        let premultiplied = Image::from_tiff(&fixture("premultiplied.tiff")).unwrap();
+        assert_eq!(
+            premultiplied.rgba(),
+            [255, 0, 0, 128, 200, 100, 50, 255, 0, 0, 0, 0, 255, 255, 255, 64]
+        );
+
+        assert!(Image::from_png(&fixture("rgb.tiff")).is_err());
+        assert!(Image::from_tiff(&fixture("rgb.png")).is_err());
+        assert!(Image::from_png(&fixture("rgb.png")[..40]).is_err());
+        assert!(Image::new(2, 2, vec![0; 15]).is_err());
+
+        let mut float = Cursor::new(Vec::new());
+        TiffEncoder::new(&mut float)
+            .unwrap()
+            .write_image::<colortype::Gray32Float>(1, 1, &[0.5])
+            .unwrap();
+        assert!(matches!(
+            Image::from_tiff(float.get_ref()),
+            Err(ClipboardError::UnsupportedType(Type::TIFF))
+        ));
+    }
+
+    #[test]
+    fn clipboard() {
+        let backend = MemoryPasteBoard::new();
+        let board = PasteBoard::with_backend(backend.clone());
+        assert!(matches!(
+            board.get_image(),
+            Err(ClipboardError::TypeUnavailable(Type::PNG))
+        ));
+
+        board
+            .write_contents(Content::Data(fixture("rgb.tiff").into()), Type::TIFF)
+            .unwrap();
+        assert_eq!(board.get_image().unwrap().rgba(), RGB);
+
+        let image = Image::from_png(&fixture("palette.png")).unwrap();
+        board.write_image(&image).unwrap();
+        assert_eq!(backend.change_count(), 2);
+        assert_eq!(board.types().unwrap(), vec![Type::PNG, Type::TIFF]);
+        assert_eq!(board.get_image().unwrap(), image);
+        let tiff = board.get_contents(Type::TIFF, false).unwrap();
+        assert_eq!(Image::from_tiff(tiff.as_bytes()).unwrap(), image);
+        let png = board.get_contents(Type::PNG, false).unwrap();
+        assert!(png.as_bytes().starts_with(b"\x89PNG"));
+    }
+}
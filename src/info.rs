@@ -0,0 +1,610 @@
+use std::collections::HashSet;
+
+use crate::{ClipboardBackend, ClipboardError, ClipboardSnapshot, Content, PasteBoard, Type};
+
+/// Image types probed by `get_image_info`, in the order they are tried.
+const IMAGE_TYPES: [Type; 3] = [Type::PNG, Type::TIFF, Type::PDF];
+
+/// How an image stores its colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorModel {
+    Gray,
+    GrayAlpha,
+    Rgb,
+    Rgba,
+    /// Colors are looked up in a palette.
+    Indexed,
+    Cmyk,
+}
+
+/// What an image's headers say about it, read without decoding any pixels.
+///
+/// PDF sizes are in points, 1/72 inch, taken from the first page box.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ImageInfo {
+    /// `Type::PNG`, `Type::TIFF` or `Type::PDF`.
+    pub ty: Type,
+    pub width: u32,
+    pub height: u32,
+    /// Horizontal and vertical resolution, when the file records one.
+    pub dpi: Option<(u32, u32)>,
+    /// Bits per sample.
+    pub bit_depth: Option<u8>,
+    pub color: Option<ColorModel>,
+    pub pages: u32,
+}
+
+impl ImageInfo {
+    /// Read the headers of PNG, TIFF or PDF `data`, telling the format
+    /// apart by its signature. Returns `None` for anything else, and for
+    /// truncated or malformed headers.
+    pub fn probe(data: &[u8]) -> Option<Self> {
+        if data.starts_with(PNG_SIGNATURE) {
+            probe_png(data)
+        } else if data.starts_with(b"II") || data.starts_with(b"MM") {
+            probe_tiff(data)
+        } else {
+            probe_pdf(data)
+        }
+    }
+}
+
+impl ClipboardSnapshot {
+    /// Headers of the first PNG, TIFF or PDF representation that can be
+    /// probed.
+    pub fn image_info(&self) -> Option<ImageInfo> {
+        self.items()
+            .iter()
+            .filter(|(ty, _)| IMAGE_TYPES.contains(ty))
+            .find_map(|(_, content)| match content {
+                Content::Data(data) => ImageInfo::probe(data),
+                _ => None,
+            })
+    }
+}
+
+impl<B: ClipboardBackend> PasteBoard<B> {
+    /// Headers of the clipboard image from `Type::PNG`, `Type::TIFF` or
+    /// `Type::PDF`, whichever comes first in that order.
+    pub fn get_image_info(&self) -> Result<ImageInfo, ClipboardError> {
+        for ty in IMAGE_TYPES {
+            match self.board.get_contents(ty.clone()) {
+                Ok(content) => {
+                    return ImageInfo::probe(content.as_bytes())
+                        .ok_or_else(|| ClipboardError::ContentMismatch(ty.clone()))
+                }
+                Err(ClipboardError::TypeUnavailable(_)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Err(ClipboardError::TypeUnavailable(Type::PNG))
+    }
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at.checked_add(4)?)?;
+    Some(u32::from_be_bytes(bytes.try_into().ok()?))
+}
+
+/// Pixels per unit to dots per inch, or `None` when unknown.
+fn dpi(x: f64, y: f64, inches_per_unit: f64) -> Option<(u32, u32)> {
+    let to_dpi = |per_unit: f64| {
+        let dpi = (per_unit / inches_per_unit).round();
+        (dpi.is_finite() && dpi >= 1.0).then_some(dpi as u32)
+    };
+    Some((to_dpi(x)?, to_dpi(y)?))
+}
+
+/// IHDR, which the spec puts first, and `pHYs` when it precedes the image
+/// data.
+fn probe_png(data: &[u8]) -> Option<ImageInfo> {
+    if data.get(12..16)? != b"IHDR" || be_u32(data, 8)? != 13 {
+        return None;
+    }
+    let width = be_u32(data, 16)?;
+    let height = be_u32(data, 20)?;
+    let bit_depth = *data.get(24)?;
+    let color = match *data.get(25)? {
+        0 => ColorModel::Gray,
+        2 => ColorModel::Rgb,
+        3 => ColorModel::Indexed,
+        4 => ColorModel::GrayAlpha,
+        6 => ColorModel::Rgba,
+        _ => return None,
+    };
+    if width == 0 || height == 0 || ![1, 2, 4, 8, 16].contains(&bit_depth) {
+        return None;
+    }
+
+    let mut dpi_found = None;
+    let mut at = 8usize;
+    while let Some(len) = be_u32(data, at) {
+        let Some(name) = data.get(at + 4..at + 8) else {
+            break;
+        };
+        match name {
+            b"IDAT" | b"IEND" => break,
+            // Unit 1 is the meter; 0 only gives the aspect ratio.
+            b"pHYs" if len >= 9 && data.get(at + 16) == Some(&1) => {
+                let x = be_u32(data, at + 8)?;
+                let y = be_u32(data, at + 12)?;
+                dpi_found = dpi(f64::from(x), f64::from(y), 1.0 / 0.0254);
+            }
+            _ => {}
+        }
+        // Length, name and CRC around the chunk data.
+        let Some(next) = usize::try_from(len)
+            .ok()
+            .and_then(|len| at.checked_add(12)?.checked_add(len))
+        else {
+            break;
+        };
+        at = next;
+    }
+
+    Some(ImageInfo {
+        ty: Type::PNG,
+        width,
+        height,
+        dpi: dpi_found,
+        bit_depth: Some(bit_depth),
+        color: Some(color),
+        pages: 1,
+    })
+}
+
+/// A classic or BigTIFF file, read through bounds-checked accessors.
+struct Tiff<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+    /// BigTIFF: 8-byte offsets and counts, 20-byte entries.
+    big: bool,
+}
+
+/// One 12- or 20-byte IFD entry.
+#[derive(Clone, Copy)]
+struct Entry {
+    tag: u16,
+    kind: u16,
+    count: u64,
+    /// Where the value field starts.
+    field: usize,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let big_endian = match data.get(..2)? {
+            b"II" => false,
+            b"MM" => true,
+            _ => return None,
+        };
+        let mut tiff = Self {
+            data,
+            big_endian,
+            big: false,
+        };
+        match tiff.uint(2, 2)? {
+            42 => {}
+            43 if tiff.uint(4, 2)? == 8 => tiff.big = true,
+            _ => return None,
+        }
+        Some(tiff)
+    }
+
+    /// The unsigned integer of `size` bytes at `at`.
+    fn uint(&self, at: usize, size: usize) -> Option<u64> {
+        let bytes = self.data.get(at..at.checked_add(size)?)?;
+        let fold = |acc: u64, byte: &u8| acc << 8 | u64::from(*byte);
+        Some(if self.big_endian {
+            bytes.iter().fold(0, fold)
+        } else {
+            bytes.iter().rev().fold(0, fold)
+        })
+    }
+
+    fn offset_size(&self) -> usize {
+        if self.big {
+            8
+        } else {
+            4
+        }
+    }
+
+    fn first_ifd(&self) -> Option<usize> {
+        let at = if self.big { 8 } else { 4 };
+        usize::try_from(self.uint(at, self.offset_size())?).ok()
+    }
+
+    /// Where the entries of the IFD at `ifd` start, how many there are, and
+    /// where the offset of the next IFD is stored.
+    fn ifd_layout(&self, ifd: usize) -> Option<(usize, usize, usize)> {
+        let count_size = if self.big { 8 } else { 2 };
+        let count = usize::try_from(self.uint(ifd, count_size)?).ok()?;
+        let first = ifd.checked_add(count_size)?;
+        let end = count.checked_mul(self.entry_size())?.checked_add(first)?;
+        // Checked before allocating, so a bogus count can't reserve memory.
+        if end > self.data.len() {
+            return None;
+        }
+        Some((first, count, end))
+    }
+
+    fn entry_size(&self) -> usize {
+        if self.big {
+            20
+        } else {
+            12
+        }
+    }
+
+    /// The entries of the IFD at `ifd`.
+    fn entries(&self, ifd: usize) -> Option<Vec<Entry>> {
+        let (first, count, _) = self.ifd_layout(ifd)?;
+        (0..count)
+            .map(|idx| {
+                let at = first + idx * self.entry_size();
+                Some(Entry {
+                    tag: self.uint(at, 2)? as u16,
+                    kind: self.uint(at + 2, 2)? as u16,
+                    count: self.uint(at + 4, self.offset_size())?,
+                    field: at + 4 + self.offset_size(),
+                })
+            })
+            .collect()
+    }
+
+    /// The offset of the IFD after the one at `ifd`, zero for the last.
+    fn next_ifd(&self, ifd: usize) -> Option<usize> {
+        let (_, _, end) = self.ifd_layout(ifd)?;
+        usize::try_from(self.uint(end, self.offset_size())?).ok()
+    }
+
+    /// Where the `count` values of `entry`, `size` bytes each, are stored.
+    fn values_at(&self, entry: Entry, size: usize) -> Option<usize> {
+        let total = usize::try_from(entry.count).ok()?.checked_mul(size)?;
+        if total <= self.offset_size() {
+            Some(entry.field)
+        } else {
+            usize::try_from(self.uint(entry.field, self.offset_size())?).ok()
+        }
+    }
+
+    /// The first value of an integer entry.
+    fn integer(&self, entry: Entry) -> Option<u64> {
+        let size = match entry.kind {
+            1 | 7 => 1,
+            3 => 2,
+            4 => 4,
+            16 => 8,
+            _ => return None,
+        };
+        if entry.count == 0 {
+            return None;
+        }
+        self.uint(self.values_at(entry, size)?, size)
+    }
+
+    /// The first value of a RATIONAL entry.
+    fn rational(&self, entry: Entry) -> Option<f64> {
+        if entry.kind != 5 || entry.count == 0 {
+            return None;
+        }
+        let at = self.values_at(entry, 8)?;
+        let numerator = self.uint(at, 4)?;
+        let denominator = self.uint(at.checked_add(4)?, 4)?;
+        (denominator != 0).then(|| numerator as f64 / denominator as f64)
+    }
+}
+
+/// The first IFD describes the image; the rest of the chain are its pages.
+fn probe_tiff(data: &[u8]) -> Option<ImageInfo> {
+    let tiff = Tiff::new(data)?;
+    let first = tiff.first_ifd()?;
+    let entries = tiff.entries(first)?;
+    let find = |tag: u16| entries.iter().copied().find(|entry| entry.tag == tag);
+    let integer = |tag: u16| find(tag).and_then(|entry| tiff.integer(entry));
+
+    let width = u32::try_from(integer(256)?).ok()?;
+    let height = u32::try_from(integer(257)?).ok()?;
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let bit_depth = integer(258).map_or(Some(1), |bits| u8::try_from(bits).ok())?;
+    let samples = integer(277).unwrap_or(1);
+    let color = match integer(262) {
+        Some(0 | 1) if samples >= 2 => Some(ColorModel::GrayAlpha),
+        Some(0 | 1) => Some(ColorModel::Gray),
+        // YCbCr is how JPEG-compressed TIFFs store RGB.
+        Some(2 | 6) if samples >= 4 => Some(ColorModel::Rgba),
+        Some(2 | 6) => Some(ColorModel::Rgb),
+        Some(3) => Some(ColorModel::Indexed),
+        Some(5) => Some(ColorModel::Cmyk),
+        _ => None,
+    };
+    let resolution = |tag: u16| find(tag).and_then(|entry| tiff.rational(entry));
+    let dpi = match (resolution(282), resolution(283), integer(296).unwrap_or(2)) {
+        (Some(x), Some(y), 2) => dpi(x, y, 1.0),
+        (Some(x), Some(y), 3) => dpi(x, y, 1.0 / 2.54),
+        _ => None,
+    };
+
+    // Offsets already seen stop a chain that loops back on itself.
+    let mut seen = HashSet::from([first]);
+    let mut pages = 1;
+    let mut next = tiff.next_ifd(first);
+    while let Some(ifd) = next.filter(|&ifd| ifd != 0 && seen.insert(ifd)) {
+        next = tiff.next_ifd(ifd);
+        // A dangling offset points at no page.
+        if next.is_some() {
+            pages += 1;
+        }
+    }
+
+    Some(ImageInfo {
+        ty: Type::TIFF,
+        width,
+        height,
+        dpi,
+        bit_depth: Some(bit_depth),
+        color,
+        pages,
+    })
+}
+
+/// PDF whitespace, per ISO 32000 7.2.2.
+fn is_pdf_space(byte: u8) -> bool {
+    matches!(byte, b'\0' | b'\t' | b'\n' | b'\x0c' | b'\r' | b' ')
+}
+
+/// Whether `byte` can continue a PDF name or number.
+fn is_pdf_regular(byte: u8) -> bool {
+    !is_pdf_space(byte) && !b"()<>[]{}/%".contains(&byte)
+}
+
+/// Offsets just past each occurrence of the name `key` in `data`.
+fn find_name<'a>(data: &'a [u8], key: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+    data.windows(key.len())
+        .enumerate()
+        .filter(move |(_, window)| *window == key)
+        .map(move |(at, _)| at + key.len())
+        .filter(move |&end| data.get(end).is_none_or(|&byte| !is_pdf_regular(byte)))
+}
+
+fn skip_space(data: &[u8], mut at: usize) -> usize {
+    while data.get(at).is_some_and(|&byte| is_pdf_space(byte)) {
+        at += 1;
+    }
+    at
+}
+
+/// The number starting at `at`, after any whitespace, and the offset past
+/// it.
+fn pdf_number(data: &[u8], at: usize) -> Option<(f64, usize)> {
+    let start = skip_space(data, at);
+    let len = data
+        .get(start..)?
+        .iter()
+        .take_while(|&&byte| byte.is_ascii_digit() || b"+-.".contains(&byte))
+        .count();
+    let number = std::str::from_utf8(&data[start..start + len]).ok()?;
+    Some((number.parse().ok()?, start + len))
+}
+
+/// Page count and the size of the first page box, found by scanning for
+/// `/Type /Page` dictionaries and `/MediaBox` arrays. Pages inside
+/// compressed object streams are invisible to this, so `/Count` from the
+/// page tree is the fallback.
+fn probe_pdf(data: &[u8]) -> Option<ImageInfo> {
+    let header = &data[..data.len().min(1024)];
+    header.windows(5).position(|window| window == b"%PDF-")?;
+
+    let (width, height) = find_name(data, b"/MediaBox").find_map(|at| {
+        let at = skip_space(data, at);
+        if data.get(at) != Some(&b'[') {
+            return None;
+        }
+        let (llx, at) = pdf_number(data, at + 1)?;
+        let (lly, at) = pdf_number(data, at)?;
+        let (urx, at) = pdf_number(data, at)?;
+        let (ury, _) = pdf_number(data, at)?;
+        let size = |len: f64| {
+            let len = len.abs().round();
+            (len.is_finite() && len >= 1.0).then_some(len.min(u32::MAX as f64) as u32)
+        };
+        Some((size(urx - llx)?, size(ury - lly)?))
+    })?;
+
+    let pages = find_name(data, b"/Type")
+        .filter(|&at| {
+            let at = skip_space(data, at);
+            let page = data.get(at..at + 5) == Some(b"/Page");
+            page && data.get(at + 5).is_none_or(|&byte| !is_pdf_regular(byte))
+        })
+        .count();
+    let pages = if pages > 0 {
+        u32::try_from(pages).unwrap_or(u32::MAX)
+    } else {
+        find_name(data, b"/Count")
+            .filter_map(|at| pdf_number(data, at))
+            .map(|(count, _)| count)
+            .filter(|count| count.fract() == 0.0 && *count >= 1.0)
+            .fold(1.0, f64::max)
+            .min(u32::MAX as f64) as u32
+    };
+
+    Some(ImageInfo {
+        ty: Type::PDF,
+        width,
+        height,
+        dpi: None,
+        bit_depth: None,
+        color: None,
+        pages,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::MemoryPasteBoard;
+
+    fn fixture(name: &str) -> Vec<u8> {
+        let path = format!("{}/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+        std::fs::read(path).unwrap()
+    }
+
+    const FIXTURES: [&str; 8] = [
+        "dpi.png",
+        "gray16.tiff",
+        "gray_alpha16.png",
+        "pages.pdf",
+        "pages.tiff",
+        "palette.png",
+        "rgb.png",
+        "rgb.tiff",
+    ];
+
+    #[test]
+    fn png() {
+        let info = ImageInfo::probe(&fixture("rgb.png")).unwrap();
+        assert_eq!(
+            info,
+            ImageInfo {
+                ty: Type::PNG,
+                width: 3,
+                height: 2,
+                dpi: None,
+                bit_depth: Some(8),
+                color: Some(ColorModel::Rgb),
+                pages: 1,
+            }
+        );
+        let info = ImageInfo::probe(&fixture("dpi.png")).unwrap();
+        assert_eq!(info.dpi, Some((144, 144)));
+        assert_eq!(info.color, Some(ColorModel::GrayAlpha));
+        let info = ImageInfo::probe(&fixture("gray_alpha16.png")).unwrap();
+        assert_eq!(info.bit_depth, Some(16));
+        let info = ImageInfo::probe(&fixture("palette.png")).unwrap();
+        assert_eq!(info.color, Some(ColorModel::Indexed));
+    }
+
+    #[test]
+    fn tiff() {
+        let info = ImageInfo::probe(&fixture("pages.tiff")).unwrap();
+        assert_eq!(
+            info,
+            ImageInfo {
+                ty: Type::TIFF,
+                width: 4,
+                height: 3,
+                dpi: Some((300, 150)),
+                bit_depth: Some(8),
+                color: Some(ColorModel::Gray),
+                pages: 2,
+            }
+        );
+        let info = ImageInfo::probe(&fixture("rgb.tiff")).unwrap();
+        assert_eq!((info.width, info.height), (3, 2));
+        assert_eq!(info.color, Some(ColorModel::Rgb));
+        assert_eq!((info.dpi, info.pages), (None, 1));
+        let info = ImageInfo::probe(&fixture("gray16.tiff")).unwrap();
+        assert_eq!((info.width, info.bit_depth), (2, Some(16)));
+
+        // The next-IFD offset of the first page pointing back at itself.
+        let mut looped = fixture("rgb.tiff");
+        let first = u32::from_le_bytes(looped[4..8].try_into().unwrap()) as usize;
+        let entries = u16::from_le_bytes([looped[first], looped[first + 1]]) as usize;
+        let next = first + 2 + entries * 12;
+        looped[next..next + 4].copy_from_slice(&(first as u32).to_le_bytes());
+        assert_eq!(ImageInfo::probe(&looped).unwrap().pages, 1);
+    }
+
+    #[test]
+    fn pdf() {
+        let info = ImageInfo::probe(&fixture("pages.pdf")).unwrap();
+        assert_eq!(
+            info,
+            ImageInfo {
+                ty: Type::PDF,
+                width: 612,
+                height: 792,
+                dpi: None,
+                bit_depth: None,
+                color: None,
+                pages: 2,
+            }
+        );
+        // Page objects hidden in an object stream.
+        let info = ImageInfo::probe(
+            b"%PDF-1.5 << /Type /Pages /Count 12 /MediaBox [0 0 595.28 841.89] >>",
+        )
+        .unwrap();
+        assert_eq!((info.width, info.height, info.pages), (595, 842, 12));
+        assert_eq!(ImageInfo::probe(b"%PDF-1.7 no boxes"), None);
+        assert_eq!(ImageInfo::probe(b"Hello world"), None);
+    }
+
+    #[test]
+    fn clipboard() {
+        let board = PasteBoard::with_backend(MemoryPasteBoard::new());
+        assert!(matches!(
+            board.get_image_info(),
+            Err(ClipboardError::TypeUnavailable(Type::PNG))
+        ));
+        board
+            .writer()
+            .add(Type::TIFF, Content::Data(fixture("pages.tiff").into()))
+            .add(Type::PDF, Content::Data(fixture("pages.pdf").into()))
+            .commit()
+            .unwrap();
+        assert_eq!(board.get_image_info().unwrap().ty, Type::TIFF);
+        let snapshot = board.snapshot().unwrap();
+        assert_eq!(snapshot.image_info().unwrap().pages, 2);
+
+        board
+            .write_contents(Content::Data(b"Hello world".to_vec().into()), Type::PNG)
+            .unwrap();
+        assert!(matches!(
+            board.get_image_info(),
+            Err(ClipboardError::ContentMismatch(Type::PNG))
+        ));
+        assert_eq!(board.snapshot().unwrap().image_info(), None);
+    }
+
+    #[test]
+    fn truncated() {
+        for name in FIXTURES {
+            let data = fixture(name);
+            assert!(ImageInfo::probe(&data).is_some(), "{name}");
+            for len in 0..data.len() {
+                ImageInfo::probe(&data[..len]);
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn arbitrary_bytes(data in prop::collection::vec(any::<u8>(), 0..512)) {
+            ImageInfo::probe(&data);
+        }
+
+        #[test]
+        fn corrupted_fixtures(
+            name in prop::sample::select(FIXTURES.to_vec()),
+            flips in prop::collection::vec((any::<prop::sample::Index>(), any::<u8>()), 1..8),
+        ) {
+            let mut data = fixture(name);
+            for (idx, byte) in flips {
+                let idx = idx.index(data.len());
+                data[idx] = byte;
+            }
+            ImageInfo::probe(&data);
+        }
+    }
+}
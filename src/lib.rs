@@ -1,34 +1,55 @@
+#[cfg(target_os = "macos")]
 #[macro_use]
 extern crate objc;
-use std::cell::RefCell;
-use std::ffi::c_void;
-use std::os::raw::c_long;
+use std::cell::Cell;
 
-use objc::runtime::{Object, NO};
-use objc_foundation::{object_struct, NSArray, NSData, NSString};
-use objc_foundation::{INSData, INSString};
-use objc_id::Id;
+#[cfg(target_os = "macos")]
+mod appkit;
+mod backend;
+mod best;
+pub mod clipsnap;
+pub mod convert;
+mod cursor;
+mod error;
+mod files;
+pub mod history;
+#[cfg(feature = "image")]
+mod image;
+mod info;
+mod memory;
+mod privacy;
+mod snapshot;
+mod sniff;
+#[cfg(feature = "async")]
+mod stream;
+mod watcher;
+mod writer;
 
-#[allow(non_upper_case_globals)]
-const NSUTF8StringEncoding: u8 = 4;
-type NSPasteboardType = *mut NSString;
+#[cfg(target_os = "macos")]
+pub use appkit::GeneralPasteBoard;
+pub use backend::ClipboardBackend;
+pub use cursor::ChangeCursor;
+pub use error::ClipboardError;
+#[cfg(feature = "image")]
+pub use image::Image;
+pub use info::{ColorModel, ImageInfo};
+pub use memory::MemoryPasteBoard;
+pub use privacy::{PrivacyMarkers, AUTO_GENERATED_TYPE, CONCEALED_TYPE, TRANSIENT_TYPE};
+pub use snapshot::ClipboardSnapshot;
+pub use sniff::sniff;
+#[cfg(feature = "async")]
+pub use stream::ClipboardChanges;
+pub use watcher::{ClipboardEvent, ClipboardWatcher, WatchOptions};
+pub use writer::{ClipboardWriter, WriteOptions};
 
-object_struct!(NSPasteboard);
+/// Backend used by `PasteBoard::new`: the system clipboard. Elsewhere there
+/// is no default, so pick one such as `MemoryPasteBoard` with
+/// `PasteBoard::with_backend`.
+#[cfg(target_os = "macos")]
+pub type DefaultBackend = GeneralPasteBoard;
 
-#[allow(improper_ctypes)]
-#[link(name = "AppKit", kind = "framework")]
-extern "C" {
-    static NSPasteboardTypeTIFF: NSPasteboardType;
-    static NSPasteboardTypePNG: NSPasteboardType;
-    static NSPasteboardTypePDF: NSPasteboardType;
-    static NSPasteboardTypeHTML: NSPasteboardType;
-    static NSPasteboardTypeRTF: NSPasteboardType;
-    static NSPasteboardTypeTabularText: NSPasteboardType;
-    static NSPasteboardTypeString: NSPasteboardType;
-    static NSPasteboardTypeFileURL: NSPasteboardType;
-}
-
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Type {
     TIFF,
     PNG,
@@ -38,169 +59,174 @@ pub enum Type {
     TabularText,
     String,
     FileUrl,
-    Other,
+    /// Any other pasteboard type, identified by its raw UTI such as
+    /// `com.microsoft.Word.Doc`.
+    Custom(String),
 }
 
-#[derive(Debug, Clone)]
+impl Type {
+    /// The uniform type identifier AppKit uses for this type.
+    pub fn uti(&self) -> &str {
+        match self {
+            Self::TIFF => "public.tiff",
+            Self::PNG => "public.png",
+            Self::PDF => "com.adobe.pdf",
+            Self::HTML => "public.html",
+            Self::RTF => "public.rtf",
+            Self::TabularText => "public.utf8-tab-separated-values-text",
+            Self::String => "public.utf8-plain-text",
+            Self::FileUrl => "public.file-url",
+            Self::Custom(uti) => uti,
+        }
+    }
+
+    /// Map a UTI to its `Type`, falling back to `Type::Custom` for
+    /// identifiers without a dedicated variant.
+    pub fn from_uti(uti: &str) -> Self {
+        match uti {
+            "public.tiff" => Self::TIFF,
+            "public.png" => Self::PNG,
+            "com.adobe.pdf" => Self::PDF,
+            "public.html" => Self::HTML,
+            "public.rtf" => Self::RTF,
+            "public.utf8-tab-separated-values-text" => Self::TabularText,
+            "public.utf8-plain-text" => Self::String,
+            "public.file-url" => Self::FileUrl,
+            _ => Self::Custom(uti.to_string()),
+        }
+    }
+
+    /// Whether AppKit hands this type out as a string rather than raw data,
+    /// so its content should be UTF-8.
+    pub fn is_text(&self) -> bool {
+        matches!(
+            self,
+            Self::FileUrl | Self::HTML | Self::RTF | Self::String | Self::TabularText
+        )
+    }
+
+    /// Fold a `Custom` UTI that names a built-in type into that variant.
+    pub(crate) fn canonical(self) -> Self {
+        match self {
+            Self::Custom(uti) => Self::from_uti(&uti),
+            ty => ty,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Content {
     Data(Box<[u8]>),
     String(Box<str>),
+    /// Rich text, rendered to HTML, RTF or plain text to match the type it
+    /// is written as.
+    Attributed(convert::AttributedText),
 }
 
-impl From<NSPasteboardType> for Type {
-    fn from(ty: NSPasteboardType) -> Self {
-        unsafe {
-            if msg_send![ty, isEqualToString: NSPasteboardTypeTIFF] {
-                Self::TIFF
-            } else if msg_send![ty, isEqualToString: NSPasteboardTypePNG] {
-                Self::PNG
-            } else if msg_send![ty, isEqualToString: NSPasteboardTypePDF] {
-                Self::PDF
-            } else if msg_send![ty, isEqualToString: NSPasteboardTypeHTML] {
-                Self::HTML
-            } else if msg_send![ty, isEqualToString: NSPasteboardTypeRTF] {
-                Self::RTF
-            } else if msg_send![ty, isEqualToString: NSPasteboardTypeTabularText] {
-                Self::TabularText
-            } else if msg_send![ty, isEqualToString: NSPasteboardTypeString] {
-                Self::String
-            } else if msg_send![ty, isEqualToString: NSPasteboardTypeFileURL] {
-                Self::FileUrl
-            } else {
-                Self::Other
-            }
+impl Content {
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::Data(data) => data,
+            Self::String(string) => string.as_bytes(),
+            Self::Attributed(text) => text.text().as_bytes(),
         }
     }
-}
 
-impl From<Type> for NSPasteboardType {
-    fn from(ty: Type) -> Self {
-        unsafe {
-            match ty {
-                Type::FileUrl => NSPasteboardTypeFileURL,
-                Type::HTML => NSPasteboardTypeHTML,
-                Type::PDF => NSPasteboardTypePDF,
-                Type::PNG => NSPasteboardTypePNG,
-                Type::RTF => NSPasteboardTypeRTF,
-                Type::String => NSPasteboardTypeString,
-                Type::TIFF => NSPasteboardTypeTIFF,
-                Type::TabularText => NSPasteboardTypeTabularText,
-                _ => unimplemented!(),
-            }
+    /// The content as text, if it is a string or UTF-8 data. Attributed
+    /// text gives its plain text.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Data(data) => std::str::from_utf8(data).ok(),
+            Self::String(string) => Some(string),
+            Self::Attributed(text) => Some(text.text()),
         }
     }
 }
 
-type Error = Box<dyn std::error::Error>;
-
 #[derive(Debug)]
-pub struct PasteBoard {
-    board: Id<NSPasteboard>,
-    change_count: RefCell<c_long>,
+pub struct PasteBoard<
+    #[cfg(target_os = "macos")] B: ClipboardBackend = DefaultBackend,
+    #[cfg(not(target_os = "macos"))] B: ClipboardBackend,
+> {
+    board: B,
+    cursor: Cell<ChangeCursor>,
+    options: WriteOptions,
 }
 
+#[cfg(target_os = "macos")]
 impl PasteBoard {
-    pub fn new() -> Result<Self, Error> {
-        unsafe {
-            let cls = class!(NSPasteboard);
-            let board: *mut NSPasteboard = msg_send![cls, generalPasteboard];
-            if board.is_null() {
-                return Err("Can't get generalPasteboard".into());
-            }
-            let board = Id::from_ptr(board);
-            Ok(Self {
-                board,
-                change_count: RefCell::new(0),
-            })
-        }
+    pub fn new() -> Result<Self, ClipboardError> {
+        Ok(Self::with_backend(GeneralPasteBoard::new()?))
     }
+}
 
-    pub fn get_contents(&self, ty: Type, newer: bool) -> Result<Content, Error> {
-        unsafe {
-            let change_count: c_long = msg_send![self.board, changeCount];
-            if newer && change_count == *self.change_count.borrow() {
-                return Err("There is no newer content to get.".into());
-            } else {
-                *self.change_count.borrow_mut() = change_count;
-            }
-            let content = match ty {
-                Type::TIFF | Type::PNG | Type::PDF => {
-                    let data: Id<NSData> = Id::from_ptr(msg_send![
-                        self.board,
-                        dataForType: NSPasteboardType::from(ty)
-                    ]);
-                    Content::Data(data.bytes().to_vec().into_boxed_slice())
-                }
-                Type::FileUrl | Type::HTML | Type::RTF | Type::String | Type::TabularText => {
-                    let string: Id<NSString> = Id::from_ptr(msg_send![
-                        self.board,
-                        stringForType: NSPasteboardType::from(ty)
-                    ]);
-                    Content::String(string.as_str().to_string().into_boxed_str())
-                }
-                _ => return Err("Unsupport other type at now".into()),
-            };
-            Ok(content)
+impl<B: ClipboardBackend> PasteBoard<B> {
+    pub fn with_backend(board: B) -> Self {
+        Self {
+            board,
+            cursor: Cell::new(ChangeCursor::at(0)),
+            options: WriteOptions::default(),
         }
     }
 
-    pub fn write_contents(&self, content: Content, ty: Type) -> Result<(), Error> {
-        unsafe {
-            match content {
-                Content::Data(data) => {
-                    let nsdata_cls = class!(NSData);
-                    let data: *mut NSData = msg_send![nsdata_cls, dataWithBytesNoCopy: (data.as_ptr() as *const c_void) 
-                                                                               length: data.len() 
-                                                                         freeWhenDone: NO];
-                    if data.is_null() {
-                        return Err("Fail to init NSData".into());
-                    }
-                    let data: Id<NSData> = Id::from_ptr(data);
-                    let _: c_long = msg_send![self.board, clearContents];
-                    if msg_send![self.board, setData: &*data forType: NSPasteboardType::from(ty)] {
-                        Ok(())
-                    } else {
-                        Err("Fail to setcontent to clipboard.".into())
-                    }
-                }
-                Content::String(string) => {
-                    let nsstring_cls = class!(NSString);
-                    let nsstring_instance: *mut Object = msg_send![nsstring_cls, alloc];
-                    let string: *mut NSString = msg_send![nsstring_instance, initWithBytesNoCopy: (string.as_ptr() as *const c_void) 
-                                                                                          length: string.len() 
-                                                                                        encoding: NSUTF8StringEncoding 
-                                                                                    freeWhenDone: NO];
-                    if string.is_null() {
-                        return Err("Fail to init NSString".into());
-                    }
-                    let string: Id<NSString> = Id::from_ptr(string);
-                    let _: c_long = msg_send![self.board, clearContents];
-                    if msg_send![self.board, setString: &*string forType: NSPasteboardType::from(ty)] {
-                        Ok(())
-                    } else {
-                        Err("Fail to setcontent to clipboard.".into())
-                    }
-                }
-            }
+    pub fn backend(&self) -> &B {
+        &self.board
+    }
+
+    /// Read `ty`. With `newer` set this fails with `NoNewContent` when the
+    /// clipboard hasn't changed since the previous `get_contents` call on
+    /// this `PasteBoard`, whatever type that call read; use
+    /// `get_contents_since` to track freshness per consumer.
+    pub fn get_contents(&self, ty: Type, newer: bool) -> Result<Content, ClipboardError> {
+        let cursor = self.cursor.replace(self.cursor());
+        if newer {
+            self.get_contents_since(&cursor, ty)
+        } else {
+            self.board.get_contents(ty)
         }
     }
 
-    pub fn types(&self) -> Result<Vec<Type>, Error> {
-        unsafe {
-            let types: Id<NSArray<NSPasteboardType>> = Id::from_ptr(msg_send![self.board, types]);
-            let types = (0u64..msg_send![types, count])
-                .filter_map(|idx| {
-                    let ty: NSPasteboardType = msg_send![types, objectAtIndex: idx];
-                    let ty = Type::from(ty);
-                    if ty == Type::Other {
-                        None
-                    } else {
-                        Some(ty)
-                    }
-                })
-                .collect();
-            Ok(types)
+    pub fn write_contents(&self, content: Content, ty: Type) -> Result<(), ClipboardError> {
+        self.publish(vec![(ty.canonical(), content)])
+    }
+
+    pub fn write_options(&self) -> &WriteOptions {
+        &self.options
+    }
+
+    /// Set the options applied to `write_contents` and `writer`.
+    pub fn set_write_options(&mut self, options: WriteOptions) {
+        self.options = options;
+    }
+
+    pub(crate) fn publish(&self, mut items: Vec<(Type, Content)>) -> Result<(), ClipboardError> {
+        self.options.validate(&items)?;
+        self.options.prepare(&mut items);
+        self.board.write_all(items)
+    }
+
+    /// Publish several pasteboard items; write options apply to the first.
+    pub(crate) fn publish_items(
+        &self,
+        mut items: Vec<Vec<(Type, Content)>>,
+    ) -> Result<(), ClipboardError> {
+        for item in &items {
+            self.options.validate(item)?;
         }
+        if let Some(first) = items.first_mut() {
+            self.options.prepare(first);
+        }
+        self.board.write_items(items)
+    }
+
+    /// Start a write that publishes several representations at once.
+    pub fn writer(&self) -> ClipboardWriter<'_, B> {
+        ClipboardWriter::new(self)
+    }
+
+    pub fn types(&self) -> Result<Vec<Type>, ClipboardError> {
+        self.board.types()
     }
 }
 
@@ -208,27 +234,15 @@ impl PasteBoard {
 mod tests {
     use super::*;
 
-    #[test]
-    fn static_link() {
-        unsafe {
-            let mut static_data = vec![
-                NSPasteboardTypeTIFF, NSPasteboardTypePNG, NSPasteboardTypePDF,
-                NSPasteboardTypeHTML, NSPasteboardTypeRTF, NSPasteboardTypeTabularText,
-                NSPasteboardTypeString, NSPasteboardTypeFileURL
-            ];
-            static_data.drain(..).for_each(|instance| {
-                let str_ptr: *const i8 = msg_send![instance, UTF8String];
-                assert!(!str_ptr.is_null());
-            })
-        }
-    }
-
     #[test]
     fn pasteboard() {
         let ori = "Hello world".to_string().into_boxed_str();
         let content = Content::String(ori.clone());
 
+        #[cfg(target_os = "macos")]
         let board = PasteBoard::new().unwrap();
+        #[cfg(not(target_os = "macos"))]
+        let board = PasteBoard::with_backend(MemoryPasteBoard::new());
         board.write_contents(content.clone(), Type::String).unwrap();
 
         let types = board.types().unwrap();
@@ -253,4 +267,41 @@ mod tests {
             panic!("Get incorrect value.");
         }
     }
+
+    #[test]
+    fn uti() {
+        let types = [
+            Type::TIFF,
+            Type::PNG,
+            Type::PDF,
+            Type::HTML,
+            Type::RTF,
+            Type::TabularText,
+            Type::String,
+            Type::FileUrl,
+            Type::Custom("org.chromium.source-url".into()),
+        ];
+        for ty in types {
+            assert_eq!(Type::from_uti(ty.uti()), ty);
+        }
+        assert_eq!(Type::Custom("public.png".into()).canonical(), Type::PNG);
+    }
+
+    #[test]
+    fn newer() {
+        let board = PasteBoard::with_backend(MemoryPasteBoard::new());
+        board
+            .write_contents(Content::String("a".into()), Type::String)
+            .unwrap();
+        assert!(board.get_contents(Type::String, true).is_ok());
+        assert!(matches!(
+            board.get_contents(Type::String, true),
+            Err(ClipboardError::NoNewContent)
+        ));
+        assert!(board.get_contents(Type::String, false).is_ok());
+        assert!(matches!(
+            board.get_contents(Type::HTML, false),
+            Err(ClipboardError::TypeUnavailable(Type::HTML))
+        ));
+    }
 }
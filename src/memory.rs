@@ -0,0 +1,296 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::{ClipboardBackend, ClipboardError, Content, Type};
+
+#[derive(Debug, Default)]
+struct State {
+    change_count: i64,
+    /// Representations of the first pasteboard item, which every per-type
+    /// call works on.
+    items: Vec<(Type, Box<[u8]>)>,
+    /// Representations of any further items.
+    more: Vec<Vec<(Type, Box<[u8]>)>>,
+}
+
+/// An in-process pasteboard that behaves like `NSPasteboard`.
+///
+/// Clones share the same storage, the way every `generalPasteboard` handle
+/// talks to the same pasteboard server.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryPasteBoard {
+    state: Arc<Mutex<State>>,
+}
+
+impl MemoryPasteBoard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Same as `-[NSPasteboard clearContents]`: drop every representation
+    /// and return the new change count.
+    pub fn clear_contents(&self) -> i64 {
+        let mut state = self.state();
+        state.items.clear();
+        state.more.clear();
+        state.change_count += 1;
+        state.change_count
+    }
+
+    /// Same as `-[NSPasteboard setData:forType:]`: replace the
+    /// representation for `ty` without touching the change count.
+    pub fn set_contents(&self, content: Content, ty: Type) -> Result<(), ClipboardError> {
+        self.state().set(content, ty);
+        Ok(())
+    }
+}
+
+impl State {
+    fn set(&mut self, content: Content, ty: Type) {
+        let ty = ty.canonical();
+        let bytes = to_bytes(content, &ty);
+        match self.items.iter_mut().find(|(item, _)| *item == ty) {
+            Some((_, item)) => *item = bytes,
+            None => self.items.push((ty, bytes)),
+        }
+    }
+}
+
+fn to_bytes(content: Content, ty: &Type) -> Box<[u8]> {
+    match content {
+        Content::Data(data) => data,
+        Content::String(string) => string.into_boxed_bytes(),
+        Content::Attributed(text) => text.render(ty).into_bytes().into_boxed_slice(),
+    }
+}
+
+/// Read stored bytes as `ty`. `stringForType:` yields nil for bytes that
+/// aren't valid text.
+fn from_bytes(bytes: Box<[u8]>, ty: &Type) -> Option<Content> {
+    if !ty.is_text() {
+        return Some(Content::Data(bytes));
+    }
+    String::from_utf8(bytes.into_vec())
+        .ok()
+        .map(|string| Content::String(string.into_boxed_str()))
+}
+
+impl ClipboardBackend for MemoryPasteBoard {
+    fn change_count(&self) -> i64 {
+        self.state().change_count
+    }
+
+    fn types(&self) -> Result<Vec<Type>, ClipboardError> {
+        Ok(self
+            .state()
+            .items
+            .iter()
+            .map(|(ty, _)| ty.clone())
+            .collect())
+    }
+
+    fn get_contents(&self, ty: Type) -> Result<Content, ClipboardError> {
+        let ty = ty.canonical();
+        let state = self.state();
+        let bytes = match state.items.iter().find(|(item, _)| *item == ty) {
+            Some((_, bytes)) => bytes.clone(),
+            None => return Err(ClipboardError::TypeUnavailable(ty)),
+        };
+        from_bytes(bytes, &ty).ok_or(ClipboardError::TypeUnavailable(ty))
+    }
+
+    fn write_all(&self, items: Vec<(Type, Content)>) -> Result<(), ClipboardError> {
+        // Hold the lock across the whole write so other handles never see
+        // a half-published set of representations.
+        let mut state = self.state();
+        state.items.clear();
+        state.more.clear();
+        state.change_count += 1;
+        for (ty, content) in items {
+            state.set(content, ty);
+        }
+        Ok(())
+    }
+
+    fn item_contents(&self, ty: Type) -> Result<Vec<Content>, ClipboardError> {
+        let ty = ty.canonical();
+        let state = self.state();
+        let contents: Vec<_> = std::iter::once(&state.items)
+            .chain(&state.more)
+            .filter_map(|item| item.iter().find(|(item, _)| *item == ty))
+            .filter_map(|(_, bytes)| from_bytes(bytes.clone(), &ty))
+            .collect();
+        if contents.is_empty() {
+            return Err(ClipboardError::TypeUnavailable(ty));
+        }
+        Ok(contents)
+    }
+
+    fn write_items(&self, items: Vec<Vec<(Type, Content)>>) -> Result<(), ClipboardError> {
+        let mut items = items.into_iter();
+        let mut state = self.state();
+        state.items.clear();
+        state.more.clear();
+        state.change_count += 1;
+        for (ty, content) in items.next().unwrap_or_default() {
+            state.set(content, ty);
+        }
+        state.more = items
+            .map(|item| {
+                let mut stored: Vec<(Type, Box<[u8]>)> = Vec::new();
+                for (ty, content) in item {
+                    let ty = ty.canonical();
+                    let bytes = to_bytes(content, &ty);
+                    stored.retain(|(item, _)| *item != ty);
+                    stored.push((ty, bytes));
+                }
+                stored
+            })
+            .collect();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn change_count() {
+        let board = MemoryPasteBoard::new();
+        assert_eq!(board.change_count(), 0);
+        board
+            .write_contents(Content::String("a".into()), Type::String)
+            .unwrap();
+        assert_eq!(board.change_count(), 1);
+        board
+            .set_contents(Content::String("<b>a</b>".into()), Type::HTML)
+            .unwrap();
+        assert_eq!(board.change_count(), 1);
+        assert_eq!(board.clear_contents(), 2);
+        assert!(board.types().unwrap().is_empty());
+    }
+
+    #[test]
+    fn per_type_storage() {
+        let board = MemoryPasteBoard::new();
+        let shared = board.clone();
+        board.clear_contents();
+        board
+            .set_contents(Content::String("a".into()), Type::String)
+            .unwrap();
+        board
+            .set_contents(Content::Data(Box::new([1, 2, 3])), Type::PNG)
+            .unwrap();
+        board
+            .set_contents(Content::String("b".into()), Type::String)
+            .unwrap();
+
+        assert_eq!(shared.types().unwrap(), vec![Type::String, Type::PNG]);
+        match shared.get_contents(Type::String).unwrap() {
+            Content::String(val) => assert_eq!(&*val, "b"),
+            _ => panic!("Get incorrect value."),
+        }
+        match shared.get_contents(Type::PNG).unwrap() {
+            Content::Data(val) => assert_eq!(&*val, &[1, 2, 3]),
+            _ => panic!("Get incorrect value."),
+        }
+        assert!(matches!(
+            shared.get_contents(Type::HTML),
+            Err(ClipboardError::TypeUnavailable(Type::HTML))
+        ));
+
+        board
+            .write_contents(Content::String("c".into()), Type::HTML)
+            .unwrap();
+        assert_eq!(shared.types().unwrap(), vec![Type::HTML]);
+    }
+
+    #[test]
+    fn write_all() {
+        let board = MemoryPasteBoard::new();
+        board
+            .write_contents(Content::String("old".into()), Type::RTF)
+            .unwrap();
+        board
+            .write_all(vec![
+                (Type::HTML, Content::String("<b>a</b>".into())),
+                (Type::String, Content::String("a".into())),
+            ])
+            .unwrap();
+        assert_eq!(board.change_count(), 2);
+        assert_eq!(board.types().unwrap(), vec![Type::HTML, Type::String]);
+    }
+
+    #[test]
+    fn custom_types() {
+        let board = MemoryPasteBoard::new();
+        let word = Type::Custom("com.microsoft.Word.Doc".into());
+        board
+            .write_all(vec![
+                (word.clone(), Content::Data(Box::new([0xd0, 0xcf]))),
+                (
+                    Type::Custom("public.html".into()),
+                    Content::String("<i>a</i>".into()),
+                ),
+            ])
+            .unwrap();
+        assert_eq!(board.types().unwrap(), vec![word.clone(), Type::HTML]);
+        match board.get_contents(word).unwrap() {
+            Content::Data(val) => assert_eq!(&*val, &[0xd0, 0xcf]),
+            _ => panic!("Get incorrect value."),
+        }
+        match board.get_contents(Type::HTML).unwrap() {
+            Content::String(val) => assert_eq!(&*val, "<i>a</i>"),
+            _ => panic!("Get incorrect value."),
+        }
+    }
+
+    #[test]
+    fn invalid_text() {
+        let board = MemoryPasteBoard::new();
+        board
+            .write_contents(Content::Data(Box::new([0xff, 0xfe])), Type::String)
+            .unwrap();
+        assert!(matches!(
+            board.get_contents(Type::String),
+            Err(ClipboardError::TypeUnavailable(Type::String))
+        ));
+    }
+
+    #[test]
+    fn several_items() {
+        let board = MemoryPasteBoard::new();
+        board
+            .write_items(vec![
+                vec![
+                    (Type::FileUrl, Content::String("file:///a".into())),
+                    (Type::String, Content::String("a".into())),
+                ],
+                vec![(Type::FileUrl, Content::String("file:///b".into()))],
+                vec![(Type::PNG, Content::Data(Box::new([1])))],
+            ])
+            .unwrap();
+        assert_eq!(board.change_count(), 1);
+        assert_eq!(board.types().unwrap(), vec![Type::FileUrl, Type::String]);
+        let urls: Vec<_> = board
+            .item_contents(Type::FileUrl)
+            .unwrap()
+            .iter()
+            .map(|content| content.as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(urls, vec!["file:///a", "file:///b"]);
+        assert!(matches!(
+            board.item_contents(Type::HTML),
+            Err(ClipboardError::TypeUnavailable(Type::HTML))
+        ));
+
+        board
+            .write_contents(Content::String("c".into()), Type::FileUrl)
+            .unwrap();
+        assert_eq!(board.item_contents(Type::FileUrl).unwrap().len(), 1);
+    }
+}
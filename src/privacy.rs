@@ -0,0 +1,123 @@
+use crate::{ClipboardBackend, ClipboardError, ClipboardSnapshot, Content, PasteBoard, Type};
+
+/// The content is a password or other secret and shouldn't be shown.
+pub const CONCEALED_TYPE: &str = "org.nspasteboard.ConcealedType";
+/// The content is only on the clipboard briefly and shouldn't be recorded.
+pub const TRANSIENT_TYPE: &str = "org.nspasteboard.TransientType";
+/// The content was put there by an app rather than copied by the user.
+pub const AUTO_GENERATED_TYPE: &str = "org.nspasteboard.AutoGeneratedType";
+
+/// The markers from nspasteboard.org that apps such as password managers
+/// add next to the real representations, asking clipboard tools to treat
+/// the content with care.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PrivacyMarkers {
+    pub concealed: bool,
+    pub transient: bool,
+    pub auto_generated: bool,
+}
+
+impl PrivacyMarkers {
+    pub fn from_types<'a>(types: impl IntoIterator<Item = &'a Type>) -> Self {
+        let mut markers = Self::default();
+        for ty in types {
+            match ty.uti() {
+                CONCEALED_TYPE => markers.concealed = true,
+                TRANSIENT_TYPE => markers.transient = true,
+                AUTO_GENERATED_TYPE => markers.auto_generated = true,
+                _ => {}
+            }
+        }
+        markers
+    }
+
+    /// Whether any marker is set, which is what history and watchers go by.
+    pub fn is_private(&self) -> bool {
+        self.concealed || self.transient || self.auto_generated
+    }
+
+    /// The marker representations to publish alongside the content. The
+    /// markers carry no data; only their presence counts.
+    pub(crate) fn items(&self) -> Vec<(Type, Content)> {
+        [
+            (self.concealed, CONCEALED_TYPE),
+            (self.transient, TRANSIENT_TYPE),
+            (self.auto_generated, AUTO_GENERATED_TYPE),
+        ]
+        .into_iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, uti)| (Type::Custom(uti.into()), Content::Data(Box::new([]))))
+        .collect()
+    }
+}
+
+impl ClipboardSnapshot {
+    pub fn privacy_markers(&self) -> PrivacyMarkers {
+        PrivacyMarkers::from_types(self.types())
+    }
+}
+
+impl<B: ClipboardBackend> PasteBoard<B> {
+    /// The privacy markers set on the current clipboard content.
+    pub fn privacy_markers(&self) -> Result<PrivacyMarkers, ClipboardError> {
+        Ok(PrivacyMarkers::from_types(&self.board.types()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemoryPasteBoard, WriteOptions};
+
+    #[test]
+    fn markers() {
+        let mut board = PasteBoard::with_backend(MemoryPasteBoard::new());
+        board
+            .write_contents(Content::String("public".into()), Type::String)
+            .unwrap();
+        assert!(!board.privacy_markers().unwrap().is_private());
+
+        // As a password manager would publish it.
+        board
+            .writer()
+            .add(Type::String, Content::String("hunter2".into()))
+            .add(Type::from_uti(CONCEALED_TYPE), Content::Data(Box::new([])))
+            .commit()
+            .unwrap();
+        let markers = board.privacy_markers().unwrap();
+        assert!(markers.concealed && !markers.transient && markers.is_private());
+        assert_eq!(board.snapshot().unwrap().privacy_markers(), markers);
+
+        board.set_write_options(WriteOptions::default().concealed(true).transient(true));
+        board
+            .write_contents(Content::String("secret".into()), Type::String)
+            .unwrap();
+        assert_eq!(
+            board.types().unwrap(),
+            vec![
+                Type::String,
+                Type::Custom(CONCEALED_TYPE.into()),
+                Type::Custom(TRANSIENT_TYPE.into()),
+            ]
+        );
+        assert_eq!(
+            board.privacy_markers().unwrap(),
+            PrivacyMarkers {
+                concealed: true,
+                transient: true,
+                auto_generated: false,
+            }
+        );
+        assert_eq!(board.get_text_lossy().unwrap(), "secret");
+
+        // A marker the caller added itself isn't published twice.
+        board
+            .writer()
+            .add(Type::String, Content::String("x".into()))
+            .add(Type::from_uti(TRANSIENT_TYPE), Content::Data(Box::new([])))
+            .commit()
+            .unwrap();
+        assert_eq!(board.types().unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{ClipboardBackend, ClipboardError, Content, PasteBoard, Type};
+
+/// Attempts at reading a consistent snapshot before settling for the last.
+const SNAPSHOT_ATTEMPTS: usize = 3;
+
+/// Every representation on the clipboard at one point in time.
+///
+/// Two snapshots compare equal when they hold the same representations in
+/// the same order, regardless of when they were taken.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClipboardSnapshot {
+    items: Vec<(Type, Content)>,
+}
+
+impl ClipboardSnapshot {
+    pub fn new(items: Vec<(Type, Content)>) -> Self {
+        Self { items }
+    }
+
+    pub fn items(&self) -> &[(Type, Content)] {
+        &self.items
+    }
+
+    pub fn into_items(self) -> Vec<(Type, Content)> {
+        self.items
+    }
+
+    pub fn types(&self) -> impl Iterator<Item = &Type> {
+        self.items.iter().map(|(ty, _)| ty)
+    }
+
+    pub fn get(&self, ty: &Type) -> Option<&Content> {
+        self.items
+            .iter()
+            .find(|(item, _)| item == ty)
+            .map(|(_, content)| content)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl<B: ClipboardBackend> PasteBoard<B> {
+    /// Capture every representation currently on the clipboard, custom UTIs
+    /// included. Types that are listed but can't be read are left out.
+    pub fn snapshot(&self) -> Result<ClipboardSnapshot, ClipboardError> {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let change_count = self.board.change_count();
+            let mut items = Vec::new();
+            for ty in self.board.types()? {
+                match self.board.get_contents(ty.clone()) {
+                    Ok(content) => items.push((ty, content)),
+                    Err(ClipboardError::TypeUnavailable(_)) => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            // Another app may have written while we were reading; try again
+            // so the snapshot doesn't mix two generations.
+            if change_count == self.board.change_count() || attempt == SNAPSHOT_ATTEMPTS {
+                return Ok(ClipboardSnapshot { items });
+            }
+        }
+    }
+
+    /// Put every representation of `snapshot` back in one transaction.
+    pub fn restore(&self, snapshot: &ClipboardSnapshot) -> Result<(), ClipboardError> {
+        self.board.write_all(snapshot.items.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::MemoryPasteBoard;
+
+    #[test]
+    fn snapshot_restore() {
+        let board = PasteBoard::with_backend(MemoryPasteBoard::new());
+        let word = Type::Custom("com.microsoft.Word.Doc".into());
+        board
+            .writer()
+            .add(Type::HTML, Content::String("<b>a</b>".into()))
+            .add(Type::String, Content::String("a".into()))
+            .add(word.clone(), Content::Data(Box::new([0xd0, 0xcf])))
+            .commit()
+            .unwrap();
+
+        let snapshot = board.snapshot().unwrap();
+        assert_eq!(
+            snapshot.types().cloned().collect::<Vec<_>>(),
+            vec![Type::HTML, Type::String, word.clone()]
+        );
+        assert_eq!(
+            snapshot.get(&word),
+            Some(&Content::Data(Box::new([0xd0, 0xcf])))
+        );
+
+        board
+            .write_contents(Content::String("snippet".into()), Type::String)
+            .unwrap();
+        assert_ne!(board.snapshot().unwrap(), snapshot);
+
+        board.restore(&snapshot).unwrap();
+        assert_eq!(board.change_count(), 3);
+        assert_eq!(board.snapshot().unwrap(), snapshot);
+    }
+
+    #[test]
+    fn hashable() {
+        let board = PasteBoard::with_backend(MemoryPasteBoard::new());
+        let mut seen = HashSet::new();
+        for text in ["a", "b", "a"] {
+            board
+                .write_contents(Content::String(text.into()), Type::String)
+                .unwrap();
+            seen.insert(board.snapshot().unwrap());
+        }
+        assert_eq!(seen.len(), 2);
+
+        board.restore(&ClipboardSnapshot::default()).unwrap();
+        assert!(board.snapshot().unwrap().is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let snapshot = ClipboardSnapshot::new(vec![
+            (Type::String, Content::String("a".into())),
+            (
+                Type::Custom("com.example.private".into()),
+                Content::Data(Box::new([1, 2])),
+            ),
+        ]);
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(
+            json,
+            r#"{"items":[["String",{"String":"a"}],[{"Custom":"com.example.private"},{"Data":[1,2]}]]}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<ClipboardSnapshot>(&json).unwrap(),
+            snapshot
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use crate::{Content, Type};
+
+const GIF: &str = "com.compuserve.gif";
+const JPEG: &str = "public.jpeg";
+const WEBP: &str = "org.webmproject.webp";
+const SVG: &str = "public.svg-image";
+
+/// How far into text to look past whitespace, comments and the XML
+/// declaration for the first tag.
+const TEXT_HEAD: usize = 1024;
+
+/// Tags that open HTML: the ones browsers sniff documents by, plus those
+/// that start the fragments apps copy.
+const HTML_TAGS: &[&[u8]] = &[
+    b"!doctype html",
+    b"html",
+    b"head",
+    b"body",
+    b"meta",
+    b"title",
+    b"style",
+    b"script",
+    b"iframe",
+    b"div",
+    b"span",
+    b"p",
+    b"a",
+    b"b",
+    b"i",
+    b"u",
+    b"em",
+    b"strong",
+    b"font",
+    b"br",
+    b"h1",
+    b"h2",
+    b"h3",
+    b"h4",
+    b"h5",
+    b"h6",
+    b"table",
+    b"ul",
+    b"ol",
+    b"li",
+    b"pre",
+    b"code",
+    b"img",
+    b"blockquote",
+];
+
+/// Identify `bytes` by their signature: PNG, TIFF (either byte order), PDF,
+/// RTF, HTML, GIF, JPEG, WebP or SVG. Formats without a dedicated `Type`
+/// come back as `Type::Custom` with their UTI.
+///
+/// ```
+/// use rich_clipboard_macos::{sniff, Type};
+///
+/// assert_eq!(sniff(b"%PDF-1.7\n"), Some(Type::PDF));
+/// assert_eq!(sniff(b"GIF89a"), Some(Type::Custom("com.compuserve.gif".into())));
+/// assert_eq!(sniff(b"Hello world"), None);
+/// ```
+pub fn sniff(bytes: &[u8]) -> Option<Type> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some(Type::PNG);
+    }
+    // Classic and BigTIFF, little- and big-endian.
+    if [b"II*\0", b"MM\0*", b"II+\0", b"MM\0+"]
+        .iter()
+        .any(|magic| bytes.starts_with(*magic))
+    {
+        return Some(Type::TIFF);
+    }
+    if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        return Some(Type::Custom(GIF.into()));
+    }
+    if bytes.starts_with(b"\xff\xd8\xff") {
+        return Some(Type::Custom(JPEG.into()));
+    }
+    if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        return Some(Type::Custom(WEBP.into()));
+    }
+    // Readers accept the header anywhere in the first kilobyte, but text
+    // that merely mentions it must not count, so only whitespace or binary
+    // junk may come before it.
+    let head = &bytes[..bytes.len().min(1024)];
+    if head.trim_ascii_start().starts_with(b"%PDF-") {
+        return Some(Type::PDF);
+    }
+    if let Some(at) = head.windows(5).position(|window| window == b"%PDF-") {
+        if std::str::from_utf8(&head[..at]).is_err() {
+            return Some(Type::PDF);
+        }
+    }
+    sniff_text(bytes)
+}
+
+/// RTF, HTML and SVG, which may be preceded by a byte order mark and
+/// whitespace.
+fn sniff_text(bytes: &[u8]) -> Option<Type> {
+    let text = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+    let text = text[..text.len().min(TEXT_HEAD)].to_ascii_lowercase();
+    let mut rest = text.trim_ascii_start();
+    if rest.starts_with(b"{\\rtf") {
+        return Some(Type::RTF);
+    }
+    // Skip what may come before the first element.
+    loop {
+        if rest.starts_with(b"<?xml") {
+            rest = &rest[after(rest, b"?>")?..];
+        } else if rest.starts_with(b"<!--") {
+            rest = &rest[after(rest, b"-->")?..];
+        } else {
+            break;
+        }
+        rest = rest.trim_ascii_start();
+    }
+    let tag = rest.strip_prefix(b"<")?;
+    if starts_with_tag(tag, b"svg") || starts_with_tag(tag, b"!doctype svg") {
+        return Some(Type::Custom(SVG.into()));
+    }
+    HTML_TAGS
+        .iter()
+        .any(|name| starts_with_tag(tag, name))
+        .then_some(Type::HTML)
+}
+
+/// The offset just past the first `end` in `text`.
+fn after(text: &[u8], end: &[u8]) -> Option<usize> {
+    let at = text.windows(end.len()).position(|window| window == end)?;
+    Some(at + end.len())
+}
+
+/// Whether `tag` opens with the element `name`, rather than a longer one
+/// that shares its prefix.
+fn starts_with_tag(tag: &[u8], name: &[u8]) -> bool {
+    tag.strip_prefix(name).is_some_and(|rest| {
+        rest.first()
+            .is_none_or(|&byte| byte == b'>' || byte == b'/' || byte.is_ascii_whitespace())
+    })
+}
+
+/// Whether `content` is plausible as `ty`: signatures must match for
+/// formats `sniff` knows, text types must be UTF-8, and HTML must not look
+/// like another format. Custom types `sniff` doesn't produce always fit.
+pub(crate) fn fits(ty: &Type, content: &Content) -> bool {
+    let bytes = match content {
+        Content::Attributed(_) => return ty.is_text(),
+        Content::String(string) => string.as_bytes(),
+        Content::Data(data) => data,
+    };
+    let sniffed = sniff(bytes);
+    match ty {
+        Type::PNG | Type::TIFF | Type::PDF | Type::RTF => sniffed.as_ref() == Some(ty),
+        Type::Custom(uti) if [GIF, JPEG, WEBP, SVG].contains(&uti.as_str()) => {
+            sniffed.as_ref() == Some(ty)
+        }
+        Type::HTML => {
+            std::str::from_utf8(bytes).is_ok()
+                && sniffed.is_none_or(|sniffed| sniffed == Type::HTML || sniffed.uti() == SVG)
+        }
+        Type::String | Type::TabularText | Type::FileUrl => std::str::from_utf8(bytes).is_ok(),
+        Type::Custom(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custom(uti: &str) -> Option<Type> {
+        Some(Type::Custom(uti.into()))
+    }
+
+    #[test]
+    fn signatures() {
+        for (bytes, ty) in [
+            (&b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"[..], Some(Type::PNG)),
+            (b"II*\0\x08\0\0\0", Some(Type::TIFF)),
+            (b"MM\0*\0\0\0\x08", Some(Type::TIFF)),
+            (b"II+\0\x08\0\0\0", Some(Type::TIFF)),
+            (b"%PDF-1.7\n", Some(Type::PDF)),
+            (b"\r\n%PDF-1.4", Some(Type::PDF)),
+            (b"\0\xff\xfejunk\n%PDF-1.4", Some(Type::PDF)),
+            (b"%!junk\n%PDF-1.4", None),
+            (b"GIF87a\x01\0", custom(GIF)),
+            (b"\xff\xd8\xff\xe0\0\x10JFIF", custom(JPEG)),
+            (b"RIFF\x24\0\0\0WEBPVP8 ", custom(WEBP)),
+            (b"RIFF\x24\0\0\0WAVEfmt ", None),
+            (b"II", None),
+            (b"", None),
+            (b"Hello world", None),
+        ] {
+            assert_eq!(sniff(bytes), ty, "{:?}", String::from_utf8_lossy(bytes));
+        }
+    }
+
+    #[test]
+    fn text() {
+        for (text, ty) in [
+            ("{\\rtf1\\ansi hi}", Some(Type::RTF)),
+            ("\u{feff}\n {\\rtf1}", Some(Type::RTF)),
+            ("<!DOCTYPE html><p>hi", Some(Type::HTML)),
+            ("<meta charset='utf-8'><b>hi</b>", Some(Type::HTML)),
+            ("  <P>one</P>", Some(Type::HTML)),
+            ("<!-- StartFragment --><table>", Some(Type::HTML)),
+            ("<p>files start with %PDF-1.7</p>", Some(Type::HTML)),
+            ("files start with %PDF-1.7", None),
+            (
+                "<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\"/>",
+                custom(SVG),
+            ),
+            (
+                "<?xml version=\"1.0\"?><!DOCTYPE svg PUBLIC><svg>",
+                custom(SVG),
+            ),
+            ("<svg>", custom(SVG)),
+            ("<?xml version=\"1.0\"?><plist>", None),
+            ("<paragraph>", None),
+            ("<!-- unterminated", None),
+            ("a < b > c", None),
+            ("{\"rtf\": 1}", None),
+        ] {
+            assert_eq!(sniff(text.as_bytes()), ty, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn fitting() {
+        let data = |bytes: &[u8]| Content::Data(bytes.into());
+        let string = |text: &str| Content::String(text.into());
+        assert!(fits(&Type::PDF, &data(b"%PDF-1.7")));
+        assert!(!fits(&Type::PDF, &data(b"Hello world")));
+        assert!(fits(
+            &Type::HTML,
+            &string("<p>files start with %PDF-1.7</p>")
+        ));
+        assert!(!fits(&Type::PNG, &data(b"GIF89a")));
+        assert!(fits(&Type::Custom(GIF.into()), &data(b"GIF89a")));
+        assert!(!fits(&Type::Custom(JPEG.into()), &data(b"GIF89a")));
+        assert!(fits(&Type::RTF, &string("{\\rtf1 hi}")));
+        assert!(!fits(&Type::RTF, &string("hi")));
+        assert!(fits(&Type::HTML, &string("Hello <b>world</b>")));
+        assert!(!fits(&Type::HTML, &string("{\\rtf1 hi}")));
+        assert!(!fits(&Type::HTML, &data(b"\xff\xfe")));
+        assert!(fits(&Type::String, &data(b"plain")));
+        assert!(!fits(&Type::String, &data(b"\x89PNG\r\n\x1a\n")));
+        assert!(fits(&Type::Custom("com.example.blob".into()), &data(b"\0")));
+        assert!(fits(&Type::HTML, &Content::Attributed("hi".into())));
+        assert!(!fits(&Type::PNG, &Content::Attributed("hi".into())));
+    }
+}
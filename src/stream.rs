@@ -0,0 +1,184 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio::task;
+
+use crate::backend::autoreleasepool;
+use crate::{
+    ClipboardBackend, ClipboardError, ClipboardEvent, ClipboardWatcher, Content, PasteBoard, Type,
+    WatchOptions,
+};
+
+/// Stream of clipboard changes returned by `PasteBoard::changes`.
+///
+/// The polling thread behind it is told to stop when the stream is
+/// dropped; the drop doesn't wait for it, so it never blocks the executor.
+#[derive(Debug)]
+pub struct ClipboardChanges {
+    events: UnboundedReceiver<ClipboardEvent>,
+    watcher: Option<ClipboardWatcher>,
+}
+
+impl ClipboardChanges {
+    /// Wait for the next change, without pulling in a `StreamExt`.
+    pub async fn next(&mut self) -> Option<ClipboardEvent> {
+        self.events.recv().await
+    }
+}
+
+impl Drop for ClipboardChanges {
+    fn drop(&mut self) {
+        if let Some(watcher) = self.watcher.take() {
+            watcher.detach();
+        }
+    }
+}
+
+impl Stream for ClipboardChanges {
+    type Item = ClipboardEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.events.poll_recv(cx)
+    }
+}
+
+fn join_error(err: task::JoinError) -> ClipboardError {
+    ClipboardError::Backend(Box::new(err))
+}
+
+impl<B: ClipboardBackend + Clone + Send + 'static> PasteBoard<B> {
+    /// Stream every clipboard change, polled with the default `WatchOptions`.
+    pub fn changes(&self) -> io::Result<ClipboardChanges> {
+        self.changes_with(WatchOptions::default())
+    }
+
+    pub fn changes_with(&self, options: WatchOptions) -> io::Result<ClipboardChanges> {
+        let (tx, events) = mpsc::unbounded_channel();
+        let watcher = ClipboardWatcher::spawn(self.board.clone(), options, move |event| {
+            let _ = tx.send(event);
+        })?;
+        Ok(ClipboardChanges {
+            events,
+            watcher: Some(watcher),
+        })
+    }
+
+    /// Read `ty` on the blocking thread pool. Unlike `get_contents` this
+    /// never consults the `newer` bookkeeping.
+    pub fn read(
+        &self,
+        ty: Type,
+    ) -> impl Future<Output = Result<Content, ClipboardError>> + Send + 'static {
+        let board = self.board.clone();
+        async move {
+            task::spawn_blocking(move || autoreleasepool(|| board.get_contents(ty)))
+                .await
+                .map_err(join_error)?
+        }
+    }
+
+    /// Publish `items` in one transaction on the blocking thread pool,
+    /// applying the `WriteOptions` like `writer` does.
+    pub fn write(
+        &self,
+        mut items: Vec<(Type, Content)>,
+    ) -> impl Future<Output = Result<(), ClipboardError>> + Send + 'static {
+        let valid = self.options.validate(&items);
+        self.options.prepare(&mut items);
+        let board = self.board.clone();
+        async move {
+            valid?;
+            task::spawn_blocking(move || autoreleasepool(|| board.write_all(items)))
+                .await
+                .map_err(join_error)?
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::MemoryPasteBoard;
+
+    #[tokio::test]
+    async fn changes() {
+        let board = PasteBoard::with_backend(MemoryPasteBoard::new());
+        let options = WatchOptions::default().interval(Duration::from_millis(5));
+        let mut changes = board.changes_with(options).unwrap();
+
+        board
+            .write(vec![
+                (Type::HTML, Content::String("<b>a</b>".into())),
+                (Type::String, Content::String("a".into())),
+            ])
+            .await
+            .unwrap();
+        let event = tokio::time::timeout(Duration::from_secs(5), changes.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.change_count, 1);
+        assert_eq!(event.types, vec![Type::HTML, Type::String]);
+
+        match board.read(Type::HTML).await.unwrap() {
+            Content::String(val) => assert_eq!(&*val, "<b>a</b>"),
+            _ => panic!("Get incorrect value."),
+        }
+        assert!(matches!(
+            board.read(Type::PNG).await,
+            Err(ClipboardError::TypeUnavailable(Type::PNG))
+        ));
+    }
+
+    /// A backend whose polls take a while, as AppKit's may.
+    #[derive(Debug, Clone)]
+    struct Slow(MemoryPasteBoard);
+
+    impl ClipboardBackend for Slow {
+        fn change_count(&self) -> i64 {
+            std::thread::sleep(Duration::from_millis(500));
+            self.0.change_count()
+        }
+
+        fn types(&self) -> Result<Vec<Type>, ClipboardError> {
+            self.0.types()
+        }
+
+        fn get_contents(&self, ty: Type) -> Result<Content, ClipboardError> {
+            self.0.get_contents(ty)
+        }
+
+        fn write_all(&self, items: Vec<(Type, Content)>) -> Result<(), ClipboardError> {
+            self.0.write_all(items)
+        }
+    }
+
+    #[tokio::test]
+    async fn drop_does_not_block() {
+        let board = PasteBoard::with_backend(Slow(MemoryPasteBoard::new()));
+        let options = WatchOptions::default().interval(Duration::from_millis(1));
+        let changes = board.changes_with(options).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let start = std::time::Instant::now();
+        drop(changes);
+        assert!(start.elapsed() < Duration::from_millis(250));
+    }
+
+    #[tokio::test]
+    async fn strict_write() {
+        let mut board = PasteBoard::with_backend(MemoryPasteBoard::new());
+        board.set_write_options(crate::WriteOptions::default().strict(true));
+        let hello = Content::Data(b"Hello world".to_vec().into());
+        assert!(matches!(
+            board.write(vec![(Type::PDF, hello)]).await,
+            Err(ClipboardError::ContentMismatch(Type::PDF))
+        ));
+        assert_eq!(board.backend().change_count(), 0);
+    }
+}
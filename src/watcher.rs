@@ -0,0 +1,294 @@
+use std::io;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::backend::autoreleasepool;
+use crate::{ClipboardBackend, PrivacyMarkers, Type};
+
+/// A clipboard change seen by a `ClipboardWatcher`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipboardEvent {
+    pub change_count: i64,
+    pub types: Vec<Type>,
+    /// When the change was first observed.
+    pub timestamp: SystemTime,
+    /// Privacy markers found among `types`.
+    pub markers: PrivacyMarkers,
+}
+
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    interval: Duration,
+    debounce: Duration,
+    skip_private: bool,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(250),
+            debounce: Duration::ZERO,
+            skip_private: false,
+        }
+    }
+}
+
+impl WatchOptions {
+    /// How often the change count is polled.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Only report a change once the change count has stayed put this long,
+    /// so a burst of writes yields a single event.
+    pub fn debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Don't report changes carrying privacy markers at all. By default
+    /// they are reported with `ClipboardEvent::markers` set.
+    pub fn skip_private(mut self, skip: bool) -> Self {
+        self.skip_private = skip;
+        self
+    }
+}
+
+/// Polls a backend's change count on a background thread.
+///
+/// Only changes made after the watcher starts are reported. Dropping the
+/// watcher stops the thread and waits for it.
+#[derive(Debug)]
+pub struct ClipboardWatcher {
+    stop: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl ClipboardWatcher {
+    /// Call `callback` on the watcher thread for every change.
+    pub fn spawn<B, F>(backend: B, options: WatchOptions, mut callback: F) -> io::Result<Self>
+    where
+        B: ClipboardBackend + Send + 'static,
+        F: FnMut(ClipboardEvent) + Send + 'static,
+    {
+        Self::start(backend, options, move |event| {
+            callback(event);
+            true
+        })
+    }
+
+    /// Deliver changes through a channel. The watcher stops by itself once
+    /// the receiver is dropped.
+    pub fn channel<B>(
+        backend: B,
+        options: WatchOptions,
+    ) -> io::Result<(Self, Receiver<ClipboardEvent>)>
+    where
+        B: ClipboardBackend + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        let watcher = Self::start(backend, options, move |event| tx.send(event).is_ok())?;
+        Ok((watcher, rx))
+    }
+
+    fn start<B, F>(backend: B, options: WatchOptions, emit: F) -> io::Result<Self>
+    where
+        B: ClipboardBackend + Send + 'static,
+        F: FnMut(ClipboardEvent) -> bool + Send + 'static,
+    {
+        let (stop, stopped) = mpsc::channel();
+        // Take the baseline before returning so writes made right after
+        // `spawn` are never mistaken for the starting state.
+        let last = backend.change_count();
+        let handle = thread::Builder::new()
+            .name("clipboard-watcher".into())
+            .spawn(move || watch(backend, last, options, stopped, emit))?;
+        Ok(Self {
+            stop: Some(stop),
+            handle: Some(handle),
+        })
+    }
+
+    /// Ask the watcher thread to exit after its current poll.
+    pub fn stop(&self) {
+        if let Some(stop) = &self.stop {
+            let _ = stop.send(());
+        }
+    }
+
+    /// Whether the watcher thread is still running.
+    pub fn is_running(&self) -> bool {
+        self.handle
+            .as_ref()
+            .is_some_and(|handle| !handle.is_finished())
+    }
+
+    /// Stop the watcher and wait for its thread, returning the panic payload
+    /// if the callback panicked.
+    pub fn join(mut self) -> thread::Result<()> {
+        self.shutdown()
+    }
+
+    /// Tell the watcher to stop without waiting for its thread, which
+    /// exits once its current poll returns.
+    #[cfg(feature = "async")]
+    pub(crate) fn detach(mut self) {
+        self.stop.take();
+        self.handle.take();
+    }
+
+    fn shutdown(&mut self) -> thread::Result<()> {
+        self.stop.take();
+        match self.handle.take() {
+            Some(handle) => handle.join(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for ClipboardWatcher {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+fn watch<B, F>(backend: B, mut last: i64, options: WatchOptions, stopped: Receiver<()>, mut emit: F)
+where
+    B: ClipboardBackend,
+    F: FnMut(ClipboardEvent) -> bool,
+{
+    let mut pending: Option<(i64, Instant, SystemTime)> = None;
+    // Dropping the sender and sending on it both end the loop.
+    while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(options.interval) {
+        let change_count = autoreleasepool(|| backend.change_count());
+        if change_count != last && pending.is_none_or(|(pending, ..)| pending != change_count) {
+            pending = Some((change_count, Instant::now(), SystemTime::now()));
+        }
+        let (change_count, _, timestamp) = match pending {
+            Some(pending) if pending.1.elapsed() >= options.debounce => pending,
+            _ => continue,
+        };
+        pending = None;
+        last = change_count;
+        let types = autoreleasepool(|| backend.types()).unwrap_or_default();
+        let markers = PrivacyMarkers::from_types(&types);
+        if options.skip_private && markers.is_private() {
+            continue;
+        }
+        let event = ClipboardEvent {
+            change_count,
+            types,
+            timestamp,
+            markers,
+        };
+        if !emit(event) {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Content, MemoryPasteBoard};
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn channel() {
+        let board = MemoryPasteBoard::new();
+        board
+            .write_contents(Content::String("before".into()), Type::String)
+            .unwrap();
+        let options = WatchOptions::default().interval(Duration::from_millis(5));
+        let (watcher, events) = ClipboardWatcher::channel(board.clone(), options).unwrap();
+
+        board
+            .write_all(vec![
+                (Type::HTML, Content::String("<b>a</b>".into())),
+                (Type::String, Content::String("a".into())),
+            ])
+            .unwrap();
+        let event = events.recv_timeout(TIMEOUT).unwrap();
+        assert_eq!(event.change_count, 2);
+        assert_eq!(event.types, vec![Type::HTML, Type::String]);
+
+        watcher.join().unwrap();
+        assert!(events.recv().is_err());
+    }
+
+    #[test]
+    fn debounce() {
+        let board = MemoryPasteBoard::new();
+        let options = WatchOptions::default()
+            .interval(Duration::from_millis(5))
+            .debounce(Duration::from_millis(200));
+        let (_watcher, events) = ClipboardWatcher::channel(board.clone(), options).unwrap();
+
+        for text in ["a", "b", "c"] {
+            board
+                .write_contents(Content::String(text.into()), Type::String)
+                .unwrap();
+            thread::sleep(Duration::from_millis(20));
+        }
+        let event = events.recv_timeout(TIMEOUT).unwrap();
+        assert_eq!(event.change_count, 3);
+        assert!(events.recv_timeout(Duration::from_millis(300)).is_err());
+    }
+
+    #[test]
+    fn callback_and_stop() {
+        let board = MemoryPasteBoard::new();
+        let (tx, rx) = mpsc::channel();
+        let options = WatchOptions::default().interval(Duration::from_millis(5));
+        let watcher = ClipboardWatcher::spawn(board.clone(), options, move |event| {
+            tx.send(event.change_count).unwrap();
+        })
+        .unwrap();
+
+        board
+            .write_contents(Content::String("a".into()), Type::String)
+            .unwrap();
+        assert_eq!(rx.recv_timeout(TIMEOUT).unwrap(), 1);
+
+        watcher.stop();
+        let started = Instant::now();
+        while watcher.is_running() {
+            assert!(started.elapsed() < TIMEOUT);
+            thread::sleep(Duration::from_millis(5));
+        }
+        watcher.join().unwrap();
+    }
+
+    #[test]
+    fn private() {
+        let board = MemoryPasteBoard::new();
+        let secret = || {
+            vec![
+                (Type::String, Content::String("hunter2".into())),
+                (
+                    Type::from_uti(crate::CONCEALED_TYPE),
+                    Content::Data(Box::new([])),
+                ),
+            ]
+        };
+        let options = WatchOptions::default().interval(Duration::from_millis(5));
+        let (_watcher, events) = ClipboardWatcher::channel(board.clone(), options.clone()).unwrap();
+        board.write_all(secret()).unwrap();
+        let event = events.recv_timeout(TIMEOUT).unwrap();
+        assert!(event.markers.concealed);
+
+        let (_watcher, events) =
+            ClipboardWatcher::channel(board.clone(), options.skip_private(true)).unwrap();
+        board.write_all(secret()).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        board
+            .write_contents(Content::String("public".into()), Type::String)
+            .unwrap();
+        let event = events.recv_timeout(TIMEOUT).unwrap();
+        assert_eq!(event.change_count, board.change_count());
+        assert!(!event.markers.is_private());
+    }
+}
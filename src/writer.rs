@@ -0,0 +1,183 @@
+use crate::{ClipboardBackend, ClipboardError, Content, PasteBoard, PrivacyMarkers, Type};
+
+/// Collects several representations of the same item and publishes them in
+/// one clipboard transaction, e.g. HTML with a plain-text fallback.
+///
+/// ```
+/// use rich_clipboard_macos::{Content, MemoryPasteBoard, PasteBoard, Type};
+///
+/// let board = PasteBoard::with_backend(MemoryPasteBoard::new());
+/// board
+///     .writer()
+///     .add(Type::HTML, Content::String("<b>Hello</b>".into()))
+///     .add(Type::String, Content::String("Hello".into()))
+///     .commit()
+///     .unwrap();
+/// ```
+#[must_use = "nothing is written until `commit` is called"]
+#[derive(Debug)]
+pub struct ClipboardWriter<'a, B: ClipboardBackend> {
+    board: &'a PasteBoard<B>,
+    items: Vec<(Type, Content)>,
+}
+
+impl<'a, B: ClipboardBackend> ClipboardWriter<'a, B> {
+    pub(crate) fn new(board: &'a PasteBoard<B>) -> Self {
+        Self {
+            board,
+            items: Vec::new(),
+        }
+    }
+
+    /// Queue `content` as `ty`. A later entry for the same type replaces
+    /// the earlier one.
+    pub fn add(mut self, ty: Type, content: Content) -> Self {
+        let ty = ty.canonical();
+        self.items.retain(|(item, _)| *item != ty);
+        self.items.push((ty, content));
+        self
+    }
+
+    /// Clear the clipboard and publish every queued representation.
+    pub fn commit(self) -> Result<(), ClipboardError> {
+        self.board.publish(self.items)
+    }
+}
+
+/// Options applied to every write made through a `PasteBoard`.
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    rich_text_counterpart: bool,
+    strict: bool,
+    concealed: bool,
+    transient: bool,
+}
+
+impl WriteOptions {
+    /// When a write carries only one of `Type::HTML` and `Type::RTF`,
+    /// convert it and publish the other too, so apps that read just one
+    /// of them keep the formatting.
+    pub fn rich_text_counterpart(mut self, enabled: bool) -> Self {
+        self.rich_text_counterpart = enabled;
+        self
+    }
+
+    /// Reject writes whose content doesn't match its declared type, such
+    /// as bytes without a PDF header written as `Type::PDF`, with
+    /// `ClipboardError::ContentMismatch`. Checked before anything is
+    /// converted or published.
+    pub fn strict(mut self, enabled: bool) -> Self {
+        self.strict = enabled;
+        self
+    }
+
+    /// Mark every write as concealed, as password managers do, so clipboard
+    /// tools that honor the marker don't show or keep it.
+    pub fn concealed(mut self, enabled: bool) -> Self {
+        self.concealed = enabled;
+        self
+    }
+
+    /// Mark every write as transient, asking clipboard tools not to record
+    /// it in their history.
+    pub fn transient(mut self, enabled: bool) -> Self {
+        self.transient = enabled;
+        self
+    }
+
+    pub(crate) fn validate(&self, items: &[(Type, Content)]) -> Result<(), ClipboardError> {
+        if !self.strict {
+            return Ok(());
+        }
+        match items
+            .iter()
+            .find(|(ty, content)| !crate::sniff::fits(ty, content))
+        {
+            Some((ty, _)) => Err(ClipboardError::ContentMismatch(ty.clone())),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn prepare(&self, items: &mut Vec<(Type, Content)>) {
+        if self.rich_text_counterpart {
+            crate::convert::add_rich_text_counterpart(items);
+        }
+        let markers = PrivacyMarkers {
+            concealed: self.concealed,
+            transient: self.transient,
+            auto_generated: false,
+        };
+        for (ty, content) in markers.items() {
+            if !items.iter().any(|(item, _)| *item == ty) {
+                items.push((ty, content));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryPasteBoard;
+
+    #[test]
+    fn single_transaction() {
+        let backend = MemoryPasteBoard::new();
+        let board = PasteBoard::with_backend(backend.clone());
+        board
+            .writer()
+            .add(Type::HTML, Content::String("<b>Hello</b>".into()))
+            .add(Type::String, Content::String("Hi".into()))
+            .add(Type::PNG, Content::Data(Box::new([0x89, b'P'])))
+            .add(Type::String, Content::String("Hello".into()))
+            .commit()
+            .unwrap();
+
+        assert_eq!(backend.change_count(), 1);
+        assert_eq!(
+            board.types().unwrap(),
+            vec![Type::HTML, Type::PNG, Type::String]
+        );
+        match board.get_contents(Type::HTML, false).unwrap() {
+            Content::String(val) => assert_eq!(&*val, "<b>Hello</b>"),
+            _ => panic!("Get incorrect value."),
+        }
+        match board.get_contents(Type::String, false).unwrap() {
+            Content::String(val) => assert_eq!(&*val, "Hello"),
+            _ => panic!("Get incorrect value."),
+        }
+    }
+
+    #[test]
+    fn strict() {
+        let backend = MemoryPasteBoard::new();
+        let mut board = PasteBoard::with_backend(backend.clone());
+        board.set_write_options(WriteOptions::default().strict(true));
+        let hello = || Content::Data(b"Hello world".to_vec().into());
+        assert!(matches!(
+            board.write_contents(hello(), Type::PDF),
+            Err(ClipboardError::ContentMismatch(Type::PDF))
+        ));
+        assert!(matches!(
+            board
+                .writer()
+                .add(Type::String, Content::String("Hi".into()))
+                .add(Type::Custom("public.png".into()), hello())
+                .commit(),
+            Err(ClipboardError::ContentMismatch(Type::PNG))
+        ));
+        assert_eq!(backend.change_count(), 0);
+
+        board
+            .writer()
+            .add(Type::PDF, Content::Data(b"%PDF-1.7\n".to_vec().into()))
+            .add(Type::HTML, Content::String("Hello <b>world</b>".into()))
+            .add(Type::String, Content::String("Hello world".into()))
+            .commit()
+            .unwrap();
+        assert_eq!(backend.change_count(), 1);
+
+        board.set_write_options(WriteOptions::default());
+        board.write_contents(hello(), Type::PDF).unwrap();
+    }
+}